@@ -2,6 +2,34 @@ import type { FuzzResult } from "./results";
 
 const base = isProduction ? window.location.origin + "/api" : "http://192.168.178.22:8080/api";
 
+// Sends the Request with the Session-Cookie and sends the User to the Login if it is missing
+async function request(path: string, init: RequestInit = {}): Promise<Response> {
+    let response = await fetch(base + path, { ...init, credentials: "include" });
+    if (response.status == 401 && window.location.hash != "#/login") {
+        window.location.hash = "#/login";
+    }
+
+    return response;
+}
+
+export async function login(name: String, password: String): Promise<boolean> {
+    let response = await request("/auth/login", {
+        method: "POST",
+        body: JSON.stringify({ "name": name, "password": password }),
+        headers: {
+            "content-type": "application/json",
+        },
+    });
+
+    return response.ok;
+}
+
+export async function logout() {
+    await request("/auth/logout", {
+        method: "POST",
+    });
+}
+
 export class Project {
     name: String;
     source: Source;
@@ -18,18 +46,94 @@ export class ProjectTarget {
     name: String;
     folder: String;
     target: {
-        CargoFuzz: {
+        CargoFuzz?: {
             name: String;
         };
+        Honggfuzz?: {
+            name: String;
+        };
+        Command?: {
+            build?: String;
+            run: Array<String>;
+            artifacts_dir: String;
+            corpus_dir?: String;
+            env: Record<string, String>;
+        };
     };
 }
 
+// The Name of the Fuzz-Target or the Command, depending on the kind of Target
+export function targetName(target: ProjectTarget): String {
+    if (target.target.CargoFuzz) {
+        return target.target.CargoFuzz.name;
+    }
+    if (target.target.Honggfuzz) {
+        return target.target.Honggfuzz.name;
+    }
+    if (target.target.Command) {
+        return target.target.Command.run.join(" ");
+    }
+    return "";
+}
+
 export async function loadResults(project: String): Promise<Array<FuzzResult>> {
-    return fetch(base + "/results?pname=" + project).then((response) => response.json());
+    return request("/results?pname=" + project).then((response) => response.json());
 }
 
 export async function loadRunning(): Promise<Array<String>> {
-    return fetch(base + "/targets").then((response) => response.json());
+    return request("/targets").then((response) => response.json());
+}
+
+export class Run {
+    id: number;
+    project: String;
+    target: String;
+    sanitizer?: String;
+    started: number;
+    finished?: number;
+    status: "Running" | "Finished" | "Failed";
+    crashes: number;
+    exceeded?: String;
+    events: Array<RunEvent>;
+    stats: Array<Stats>;
+}
+
+export class Stats {
+    worker: number;
+    execs: number;
+    execs_per_sec: number;
+    coverage: number;
+    corpus: number;
+    rss_mb: number;
+}
+
+export type RunEvent =
+    | { type: "Cloning" }
+    | { type: "Building" }
+    | { type: "Fuzzing", workers: number }
+    | ({ type: "Stats" } & Stats)
+    | { type: "Artifact", name: String }
+    | { type: "Finished", crashes: number, exceeded?: String }
+    | { type: "Failed" };
+
+export type Update =
+    | { type: "RunStarted", run: number, project: String, target: String, sanitizer?: String }
+    | { type: "Run", run: number, project: String, target: String, sanitizer?: String, event: RunEvent }
+    | { type: "ResultStored", project: String, target: String, sanitizer?: String }
+    | { type: "ProjectChanged", project: String }
+    | { type: "ProjectRemoved", project: String };
+
+export async function loadRuns(project?: String): Promise<Array<Run>> {
+    let query = project ? "?pname=" + project : "";
+    return request("/runs" + query).then((response) => response.json());
+}
+
+// Calls the Handler for every Update pushed by the Server, returns a Function to unsubscribe
+export function subscribe(handler: (update: Update) => void): () => void {
+    let source = new EventSource(base + "/updates", { withCredentials: true });
+    source.onmessage = (message) => handler(JSON.parse(message.data));
+
+    return () => source.close();
 }
 
 export async function run(project_name: String, name: String) {
@@ -38,7 +142,7 @@ export async function run(project_name: String, name: String) {
         "name": name,
     };
 
-    return fetch(base + "/run", {
+    return request("/run", {
         method: 'POST',
         body: JSON.stringify(config),
         headers: {
@@ -48,7 +152,7 @@ export async function run(project_name: String, name: String) {
 }
 
 export async function load_projects(): Promise<Array<Project>> {
-    return fetch(base + "/projects/list").then((response) => response.json());
+    return request("/projects/list").then((response) => response.json());
 }
 
 export async function addProject(name: String, repo: String) {
@@ -62,7 +166,7 @@ export async function addProject(name: String, repo: String) {
         "targets": []
     };
 
-    fetch(base + "/projects/update", {
+    request("/projects/update", {
         method: "POST",
         body: JSON.stringify(config),
         headers: {
@@ -72,7 +176,7 @@ export async function addProject(name: String, repo: String) {
 }
 
 export async function removeProject(name: String) {
-    fetch(base + "/projects/remove?pname=" + name, {
+    request("/projects/remove?pname=" + name, {
         method: "POST"
     });
 }
@@ -89,7 +193,7 @@ export async function addProjectTarget(pname: String, tname: String, folder: Str
         "repeating": false,
     };
 
-    fetch(base + "/projects/targets/add?pname=" + pname, {
+    request("/projects/targets/add?pname=" + pname, {
         method: "POST",
         body: JSON.stringify(config),
         headers: {
@@ -99,7 +203,7 @@ export async function addProjectTarget(pname: String, tname: String, folder: Str
 }
 
 export async function removeProjectTarget(pname: String, tname: String) {
-    fetch(base + "/projects/targets/remove?pname=" + pname + "&name=" + tname, {
+    request("/projects/targets/remove?pname=" + pname + "&name=" + tname, {
         method: "POST",
     });
 }
\ No newline at end of file
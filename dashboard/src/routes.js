@@ -3,12 +3,14 @@ import NotFound from './routes/NotFound.svelte';
 import Results from "./routes/Results.svelte";
 import Running from "./routes/Running.svelte";
 import Project from "./routes/Project.svelte";
+import Login from "./routes/Login.svelte";
 
 export default {
     '/': Projects,
     '/running': Running,
     '/results': Results,
     '/project/:name': Project,
+    '/login': Login,
     // The catch-all route must always be last
     '*': NotFound
 };
@@ -0,0 +1,255 @@
+//! The Agent-Mode of cfuzz
+//!
+//! An Agent registers itself with a Coordinator, pulls Jobs from it and runs them using a local
+//! Runner. See [`crate::runner::remote`] for the Coordinator side.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::sync::{mpsc, oneshot};
+
+use crate::runner::{
+    remote::{Heartbeat, HeartbeatResponse, Job, JobFinished, Registered, Registration},
+    Event, Runner, Stop,
+};
+
+/// The Interval in which the Agent sends Heartbeats to the Coordinator
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+/// The Interval in which the Agent asks for new Jobs while it is idle
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The Cancel-Handles of all the Jobs currently running on the Agent
+type RunningJobs = Arc<Mutex<HashMap<u64, oneshot::Sender<Stop>>>>;
+
+/// The Connection of an Agent to its Coordinator
+#[derive(Clone)]
+struct Connection {
+    client: reqwest::Client,
+    coordinator: String,
+    name: String,
+    id: Arc<Mutex<String>>,
+}
+
+impl Connection {
+    fn url(&self, path: &str) -> String {
+        let id = self.id.lock().unwrap().clone();
+        format!("{}/api/agents/{}/{}", self.coordinator, id, path)
+    }
+
+    /// Registers the Agent with the Coordinator, retrying until it succeeds
+    async fn register(&self) {
+        loop {
+            let response = self
+                .client
+                .post(format!("{}/api/agents/register", self.coordinator))
+                .json(&Registration {
+                    name: self.name.clone(),
+                })
+                .send()
+                .await;
+
+            match response {
+                Ok(r) => match r.json::<Registered>().await {
+                    Ok(registered) => {
+                        println!("Registered as {}", registered.id);
+                        *self.id.lock().unwrap() = registered.id;
+                        return;
+                    }
+                    Err(e) => println!("Registering: {}", e),
+                },
+                Err(e) => println!("Registering: {}", e),
+            };
+
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Sends a single Request to the Coordinator, registering again if the Coordinator no longer
+    /// knows about this Agent
+    async fn post<B>(&self, path: &str, body: B) -> Option<reqwest::Response>
+    where
+        B: Fn(reqwest::RequestBuilder) -> reqwest::RequestBuilder,
+    {
+        let response = body(self.client.post(self.url(path))).send().await.ok()?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            self.register().await;
+            return None;
+        }
+
+        Some(response)
+    }
+
+    async fn log(&self, job: u64, line: String) {
+        let _ = self
+            .post(&format!("jobs/{}/log", job), |r| r.json(&[&line]))
+            .await;
+    }
+}
+
+/// Runs the Agent until the Process is stopped.
+///
+/// The Agent will run at most `slots` Jobs at the same time and authenticates itself using the
+/// API-Token, which needs the `agent` Scope
+pub async fn run<R>(
+    coordinator: String,
+    name: String,
+    slots: usize,
+    token: Option<String>,
+    runner: Arc<R>,
+) where
+    R: Runner + Send + Sync + 'static,
+{
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Some(token) = token {
+        let value = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token))
+            .expect("Invalid API-Token");
+        headers.insert(reqwest::header::AUTHORIZATION, value);
+    }
+
+    let connection = Connection {
+        client: reqwest::Client::builder()
+            .default_headers(headers)
+            .build()
+            .unwrap(),
+        coordinator: coordinator.trim_end_matches('/').to_string(),
+        name,
+        id: Arc::new(Mutex::new(String::new())),
+    };
+    connection.register().await;
+
+    let running: RunningJobs = Arc::new(Mutex::new(HashMap::new()));
+
+    tokio::spawn(heartbeat(connection.clone(), running.clone()));
+
+    loop {
+        if running.lock().unwrap().len() >= slots.max(1) {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            continue;
+        }
+
+        let job = match connection.post("jobs/next", |r| r).await {
+            Some(r) => r.json::<Option<Job>>().await.ok().flatten(),
+            None => None,
+        };
+
+        match job {
+            Some(job) => {
+                let (cancel, recv) = oneshot::channel();
+                running.lock().unwrap().insert(job.id, cancel);
+
+                tokio::spawn(run_job(
+                    connection.clone(),
+                    runner.clone(),
+                    running.clone(),
+                    job,
+                    recv,
+                ));
+            }
+            None => {
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        };
+    }
+}
+
+async fn heartbeat(connection: Connection, running: RunningJobs) {
+    loop {
+        tokio::time::sleep(HEARTBEAT_INTERVAL).await;
+
+        let jobs: Vec<u64> = running.lock().unwrap().keys().copied().collect();
+        let stats = serde_json::json!({
+            "jobs": jobs.len(),
+            "load": std::fs::read_to_string("/proc/loadavg")
+                .ok()
+                .and_then(|l| l.split(' ').next().map(|l| l.to_string())),
+        });
+
+        let heartbeat = Heartbeat { jobs, stats };
+        let response = match connection.post("heartbeat", |r| r.json(&heartbeat)).await {
+            Some(r) => r.json::<HeartbeatResponse>().await.ok(),
+            None => None,
+        };
+
+        let response = match response {
+            Some(r) => r,
+            None => continue,
+        };
+
+        let stops = response
+            .cancel
+            .into_iter()
+            .map(|id| (id, Stop::Cancel))
+            .chain(response.stop.into_iter().map(|id| (id, Stop::Budget)));
+        for (id, stop) in stops {
+            if let Some(sender) = running.lock().unwrap().remove(&id) {
+                let _ = sender.send(stop);
+            }
+        }
+    }
+}
+
+async fn run_job<R>(
+    connection: Connection,
+    runner: Arc<R>,
+    running: RunningJobs,
+    job: Job,
+    cancel: oneshot::Receiver<Stop>,
+) where
+    R: Runner + Send + Sync + 'static,
+{
+    let id = job.id;
+    connection
+        .log(
+            id,
+            format!(
+                "Starting {}/{}",
+                job.target.project_name(),
+                job.target.name()
+            ),
+        )
+        .await;
+
+    // The Events are passed on to the Coordinator while the Job is running
+    let (events, mut recv) = mpsc::unbounded_channel::<Event>();
+    let forward = {
+        let connection = connection.clone();
+        tokio::spawn(async move {
+            while let Some(event) = recv.recv().await {
+                let _ = connection
+                    .post(&format!("jobs/{}/events", id), |r| r.json(&[&event]))
+                    .await;
+            }
+        })
+    };
+
+    let result = runner.run(job.target, events, cancel).await;
+    let _ = forward.await;
+
+    running.lock().unwrap().remove(&id);
+
+    let success = result.is_some();
+    let output = result.unwrap_or_default();
+    for artifact in output.artifacts {
+        let _ = connection
+            .post(&format!("jobs/{}/artifact", id), |r| {
+                r.body(artifact.clone())
+            })
+            .await;
+    }
+
+    connection
+        .log(id, format!("Finished, success: {}", success))
+        .await;
+    let _ = connection
+        .post(&format!("jobs/{}/finish", id), |r| {
+            r.json(&JobFinished {
+                success,
+                exceeded: output.exceeded,
+            })
+        })
+        .await;
+}
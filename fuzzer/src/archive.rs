@@ -0,0 +1,365 @@
+//! Export and Import of Projects
+//!
+//! An Archive is a tar-File containing a `manifest.json`, which describes the Projects with their
+//! Targets, Members and Results, and a `blobs/` Folder with the Contents of the Results and of the
+//! Corpus-Entries. Every Blob is named after the SHA-256 Hash of its Content, so that duplicates
+//! are only stored once.
+//!
+//! Importing only uses the [`StorageHandle`](crate::storage::StorageHandle), so an Archive can be
+//! restored into any Storage-Backend.
+
+use std::{
+    collections::HashMap,
+    io::Read,
+    path::{Component, Path},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::Member,
+    project::{Project, Sanitizer},
+    storage::blobs::hash,
+    FuzzResult, STATE,
+};
+
+/// The Name of the Manifest in the Archive
+pub const MANIFEST: &str = "manifest.json";
+/// The Folder containing the Blobs in the Archive
+const BLOBS: &str = "blobs";
+/// The Version of the Archive-Format
+const VERSION: u32 = 1;
+
+/// Describes the Contents of an Archive
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub projects: Vec<ProjectExport>,
+}
+
+/// A single exported Project, including its Targets with their Schedules
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectExport {
+    pub project: Project,
+    pub members: Vec<Member>,
+    pub results: Vec<ResultExport>,
+    pub corpora: Vec<CorpusExport>,
+}
+
+/// A Result of a Project, whose Content is stored as a Blob
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResultExport {
+    pub target: String,
+    pub sanitizer: Option<Sanitizer>,
+    pub blob: String,
+}
+
+/// The shared Corpus of a Target, where every Entry is stored as a Blob
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CorpusExport {
+    pub target: String,
+    pub blobs: Vec<String>,
+}
+
+/// How Projects that already exist are handled when importing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Conflict {
+    /// Nothing is imported if any of the Projects already exists
+    #[default]
+    Fail,
+    /// Keeps the existing Projects and only imports the new ones
+    Skip,
+    /// Removes the existing Projects, with their Results and Corpora, before importing them
+    Replace,
+}
+
+impl Conflict {
+    /// The Name of the Strategy as used by the API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Fail => "fail",
+            Self::Skip => "skip",
+            Self::Replace => "replace",
+        }
+    }
+}
+
+/// The Outcome of an Import
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    /// The Names of the imported Projects
+    pub imported: Vec<String>,
+    /// The Names of the Projects that already existed and were skipped
+    pub skipped: Vec<String>,
+    /// The Number of imported Results
+    pub results: usize,
+    /// The Number of imported Corpus-Entries
+    pub corpus: usize,
+}
+
+/// Exports the Projects, or all of them if no Names are given, as an Archive.
+///
+/// The Corpora are read from the Workspace, as they are not part of the Storage
+pub async fn export(workspace: &Path, names: &[String]) -> Result<Vec<u8>, String> {
+    let store = &STATE.get().unwrap().store;
+
+    let mut projects = store.load_projects().await?;
+    for name in names.iter() {
+        if !projects.iter().any(|p| &p.name == name) {
+            return Err(format!("Unknown Project {:?}", name));
+        }
+    }
+    if !names.is_empty() {
+        projects.retain(|p| names.contains(&p.name));
+    }
+
+    let mut blobs: HashMap<String, Vec<u8>> = HashMap::new();
+    let mut manifest = Manifest {
+        version: VERSION,
+        projects: Vec::new(),
+    };
+    for project in projects {
+        let members = store.load_members(project.name.clone()).await?;
+
+        let results = store
+            .load_results(project.name.clone())
+            .await?
+            .into_iter()
+            .map(|result| ResultExport {
+                target: result.name,
+                sanitizer: result.sanitizer,
+                blob: add_blob(&mut blobs, result.content),
+            })
+            .collect();
+
+        let mut corpora = Vec::new();
+        for target in project.targets.iter() {
+            let folder = workspace
+                .join(&project.name)
+                .join(".corpus")
+                .join(&target.name);
+            let entries = match std::fs::read_dir(&folder) {
+                Ok(e) => e,
+                Err(_) => continue,
+            };
+
+            let mut corpus = CorpusExport {
+                target: target.name.clone(),
+                blobs: Vec::new(),
+            };
+            for entry in entries.filter_map(|e| e.ok()) {
+                if !entry.file_type().map(|t| t.is_file()).unwrap_or(false) {
+                    continue;
+                }
+
+                let content = std::fs::read(entry.path())
+                    .map_err(|e| format!("Reading {}: {}", entry.path().display(), e))?;
+                corpus.blobs.push(add_blob(&mut blobs, content));
+            }
+            corpora.push(corpus);
+        }
+
+        manifest.projects.push(ProjectExport {
+            project,
+            members,
+            results,
+            corpora,
+        });
+    }
+
+    let mut builder = tar::Builder::new(Vec::new());
+    let content = serde_json::to_vec_pretty(&manifest).unwrap();
+    append(&mut builder, MANIFEST, &content)?;
+    for (hash, content) in blobs.iter() {
+        append(&mut builder, &format!("{}/{}", BLOBS, hash), content)?;
+    }
+
+    builder.into_inner().map_err(|e| e.to_string())
+}
+
+/// Restores all the Projects of the Archive, where existing Projects are handled according to
+/// the Conflict-Strategy.
+///
+/// The whole Archive is checked before anything is changed, so an invalid Archive does not result
+/// in a partial Import
+pub async fn import(
+    workspace: &Path,
+    archive: &[u8],
+    conflict: Conflict,
+) -> Result<ImportReport, String> {
+    let (manifest, blobs) = read(archive)?;
+    if manifest.version != VERSION {
+        return Err(format!("Unsupported Archive Version {}", manifest.version));
+    }
+
+    for export in manifest.projects.iter() {
+        check_component("Project", &export.project.name)?;
+        for target in export.project.targets.iter() {
+            check_component("Target", &target.name)?;
+            target
+                .validate()
+                .map_err(|e| format!("Target {:?}: {}", target.name, e))?;
+        }
+
+        let referenced = export
+            .results
+            .iter()
+            .map(|r| &r.blob)
+            .chain(export.corpora.iter().flat_map(|c| c.blobs.iter()));
+        for blob in referenced {
+            if !blobs.contains_key(blob) {
+                return Err(format!("Missing Blob {}", blob));
+            }
+        }
+        for corpus in export.corpora.iter() {
+            check_component("Target", &corpus.target)?;
+        }
+    }
+
+    let store = &STATE.get().unwrap().store;
+
+    let existing: Vec<String> = store
+        .load_projects()
+        .await?
+        .into_iter()
+        .map(|p| p.name)
+        .filter(|name| manifest.projects.iter().any(|e| &e.project.name == name))
+        .collect();
+    if conflict == Conflict::Fail && !existing.is_empty() {
+        return Err(format!("Projects already exist: {}", existing.join(", ")));
+    }
+
+    let mut report = ImportReport::default();
+    for export in manifest.projects {
+        let name = export.project.name.clone();
+        let project_dir = workspace.join(&name);
+
+        if existing.contains(&name) {
+            if conflict == Conflict::Skip {
+                report.skipped.push(name);
+                continue;
+            }
+
+            store.remove_project(name.clone()).await?;
+            let _ = std::fs::remove_dir_all(project_dir.join(".corpus"));
+        }
+
+        let targets = export.project.targets;
+        store
+            .update_project(Project {
+                name: name.clone(),
+                source: export.project.source,
+                targets: Vec::new(),
+            })
+            .await?;
+        for target in targets {
+            store.add_project_target(name.clone(), target).await?;
+        }
+
+        for member in export.members {
+            // The Users are not part of the Archive, so they might not exist on this Instance
+            if store.load_user(member.user.clone()).await?.is_none() {
+                println!(
+                    "Skipping Member {:?} of {:?}: Unknown User",
+                    member.user, name
+                );
+                continue;
+            }
+            store.store_member(member).await?;
+        }
+
+        for result in export.results {
+            store
+                .store_result(
+                    name.clone(),
+                    FuzzResult::new(result.target, blobs[&result.blob].clone(), result.sanitizer),
+                )
+                .await?;
+            report.results += 1;
+        }
+
+        for corpus in export.corpora {
+            let folder = project_dir.join(".corpus").join(&corpus.target);
+            std::fs::create_dir_all(&folder)
+                .map_err(|e| format!("Creating {}: {}", folder.display(), e))?;
+
+            for blob in corpus.blobs {
+                let path = folder.join(&blob);
+                std::fs::write(&path, &blobs[&blob])
+                    .map_err(|e| format!("Writing {}: {}", path.display(), e))?;
+                report.corpus += 1;
+            }
+        }
+
+        report.imported.push(name);
+    }
+
+    Ok(report)
+}
+
+/// Stores the Content under its Hash and returns the Hash
+fn add_blob(blobs: &mut HashMap<String, Vec<u8>>, content: Vec<u8>) -> String {
+    let hash = hash(&content);
+    blobs.entry(hash.clone()).or_insert(content);
+    hash
+}
+
+fn append(builder: &mut tar::Builder<Vec<u8>>, path: &str, content: &[u8]) -> Result<(), String> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(crate::now());
+
+    builder
+        .append_data(&mut header, path, content)
+        .map_err(|e| format!("Writing {}: {}", path, e))
+}
+
+/// Reads the Manifest and all the Blobs from the Archive, where every Blob is checked against
+/// its Hash
+fn read(archive: &[u8]) -> Result<(Manifest, HashMap<String, Vec<u8>>), String> {
+    let mut manifest = None;
+    let mut blobs = HashMap::new();
+
+    let mut archive = tar::Archive::new(archive);
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Invalid Archive: {}", e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Invalid Archive: {}", e))?;
+        let path = entry
+            .path()
+            .map_err(|e| format!("Invalid Archive: {}", e))?
+            .to_string_lossy()
+            .to_string();
+
+        let mut content = Vec::new();
+        entry
+            .read_to_end(&mut content)
+            .map_err(|e| format!("Reading {}: {}", path, e))?;
+
+        if path == MANIFEST {
+            let parsed = serde_json::from_slice(&content)
+                .map_err(|e| format!("Parsing {}: {}", MANIFEST, e))?;
+            manifest = Some(parsed);
+        } else if let Some(name) = path.strip_prefix(&format!("{}/", BLOBS)) {
+            if hash(&content) != name {
+                return Err(format!("Corrupted Blob {}", name));
+            }
+            blobs.insert(name.to_string(), content);
+        }
+    }
+
+    let manifest = manifest.ok_or_else(|| format!("Missing {}", MANIFEST))?;
+    Ok((manifest, blobs))
+}
+
+/// Makes sure that the Name can be used as a single Folder in the Workspace
+fn check_component(kind: &str, name: &str) -> Result<(), String> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(()),
+        _ => Err(format!("Invalid {} name: {:?}", kind, name)),
+    }
+}
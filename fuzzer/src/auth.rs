@@ -0,0 +1,561 @@
+//! Authentication for the HTTP API
+//!
+//! Users log into the Dashboard with their Password, which creates a Session that is stored in a
+//! Cookie. Scripts and Agents instead use API-Tokens, which are send as a Bearer Token and only
+//! allow for the Scopes they were created with. Only Hashes of the Passwords, Sessions and Tokens
+//! are ever stored.
+
+use std::{io::Write, os::unix::fs::OpenOptionsExt, path::Path};
+
+use argon2::{
+    password_hash::{rand_core::OsRng, rand_core::RngCore, SaltString},
+    Argon2, PasswordHash, PasswordHasher, PasswordVerifier,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use warp::{
+    http::{header, Method},
+    hyper::StatusCode,
+    path::FullPath,
+    reject::Reject,
+    Filter, Rejection, Reply,
+};
+
+use crate::{now, storage::StorageError, STATE};
+
+/// The Name of the Cookie that contains the Session
+const SESSION_COOKIE: &str = "session";
+/// How long a Session stays valid after logging in
+const SESSION_DURATION: u64 = 7 * 24 * 60 * 60;
+/// The File in the Workspace the Password of the first Admin is written to
+const ADMIN_PASSWORD_FILE: &str = "admin-password";
+
+/// A User of the Dashboard and API
+#[derive(Debug, Clone, PartialEq)]
+pub struct User {
+    pub name: String,
+    /// The Argon2 Hash of the Password in the PHC Format
+    pub password_hash: String,
+    /// Admins can manage the Users
+    pub admin: bool,
+}
+
+/// A logged in Session of a User
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    /// The SHA-256 Hash of the Session-Token
+    pub token_hash: String,
+    pub user: String,
+    /// The Time at which the Session expires in Seconds since the Unix-Epoch
+    pub expires: u64,
+}
+
+/// The different Things an API-Token can be allowed to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Reading Projects, Results and Runs
+    Read,
+    /// Changing Projects and their Targets
+    Write,
+    /// Starting Runs
+    Run,
+    /// Acting as an Agent for a Coordinator
+    Agent,
+}
+
+/// An API-Token of a User
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApiToken {
+    pub user: String,
+    /// The Name of the Token, unique for the User
+    pub name: String,
+    /// The SHA-256 Hash of the Token
+    #[serde(skip)]
+    pub token_hash: String,
+    pub scopes: Vec<Scope>,
+    /// The Creation Time in Seconds since the Unix-Epoch
+    pub created: u64,
+}
+
+/// The Role of a User in a Project, Admins implicitly have every Role in all Projects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Can only read the Project and its Results
+    Viewer,
+    /// Can also edit the Targets, start Runs and triage the Crashes
+    Maintainer,
+}
+
+/// The Membership of a User in a Project
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Member {
+    pub project: String,
+    pub user: String,
+    pub role: Role,
+}
+
+/// The User on whose behalf a Request is made
+#[derive(Debug, Clone)]
+pub struct Identity {
+    pub user: String,
+    pub admin: bool,
+    /// The Scopes of the API-Token, Sessions are allowed to do everything except for acting as an
+    /// Agent
+    pub scopes: Option<Vec<Scope>>,
+}
+
+impl Identity {
+    pub fn allows(&self, scope: Scope) -> bool {
+        match &self.scopes {
+            Some(scopes) => scopes.contains(&scope),
+            None => scope != Scope::Agent,
+        }
+    }
+}
+
+/// If the User has at least the Role in the Project
+pub async fn authorized(
+    identity: &Identity,
+    project: &str,
+    role: Role,
+) -> Result<bool, StorageError> {
+    if identity.admin {
+        return Ok(true);
+    }
+
+    let store = &STATE.get().unwrap().store;
+    Ok(store
+        .load_memberships(identity.user.clone())
+        .await?
+        .iter()
+        .any(|m| m.project == project && m.role >= role))
+}
+
+/// The Projects the User can see, which is every Project for Admins
+pub async fn visible_projects(identity: &Identity) -> Result<Option<Vec<String>>, StorageError> {
+    if identity.admin {
+        return Ok(None);
+    }
+
+    let store = &STATE.get().unwrap().store;
+    let memberships = store.load_memberships(identity.user.clone()).await?;
+    Ok(Some(memberships.into_iter().map(|m| m.project).collect()))
+}
+
+/// The Request was not authenticated
+#[derive(Debug)]
+pub struct Unauthorized;
+impl Reject for Unauthorized {}
+
+/// The authenticated User is not allowed to perform the Request
+#[derive(Debug)]
+pub struct Forbidden;
+impl Reject for Forbidden {}
+
+/// Hashes the Password for storing it
+pub fn hash_password(password: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("Hashing with the default Parameters can not fail")
+        .to_string()
+}
+
+/// Checks the Password against the stored Hash
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    let hash = match PasswordHash::new(hash) {
+        Ok(h) => h,
+        Err(_) => return false,
+    };
+
+    Argon2::default()
+        .verify_password(password.as_bytes(), &hash)
+        .is_ok()
+}
+
+/// Generates a new random Token, used for Sessions and API-Tokens
+pub fn generate_token() -> String {
+    let mut bytes = [0; 32];
+    OsRng.fill_bytes(&mut bytes);
+
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The Tokens are random, so a simple Hash is enough to protect them
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Looks up the User for the Session-Cookie or the API-Token of the Request
+async fn identify(
+    session: Option<String>,
+    authorization: Option<String>,
+) -> Result<Option<Identity>, StorageError> {
+    let store = match STATE.get() {
+        Some(state) => &state.store,
+        None => return Ok(None),
+    };
+
+    let (user, scopes) = match (authorization, session) {
+        (Some(authorization), _) => {
+            let token = match authorization.strip_prefix("Bearer ") {
+                Some(t) => t,
+                None => return Ok(None),
+            };
+            let token = match store.load_token(hash_token(token.trim())).await? {
+                Some(t) => t,
+                None => return Ok(None),
+            };
+
+            (token.user, Some(token.scopes))
+        }
+        (None, Some(session)) => {
+            let token_hash = hash_token(&session);
+            let session = match store.load_session(token_hash.clone()).await? {
+                Some(s) => s,
+                None => return Ok(None),
+            };
+            if session.expires < now() {
+                store.remove_session(token_hash).await?;
+                return Ok(None);
+            }
+
+            (session.user, None)
+        }
+        (None, None) => return Ok(None),
+    };
+
+    Ok(store.load_user(user).await?.map(|user| Identity {
+        user: user.name,
+        admin: user.admin,
+        scopes,
+    }))
+}
+
+/// The Scope needed for the Request
+fn required_scope(method: &Method, path: &str) -> Scope {
+    if method == Method::GET {
+        Scope::Read
+    } else if path == "/api/run" {
+        Scope::Run
+    } else if path.starts_with("/api/agents/") {
+        Scope::Agent
+    } else {
+        Scope::Write
+    }
+}
+
+/// Extracts the Identity of the authenticated User, rejecting the Request if it is not
+/// authenticated or its Token lacks the Scope needed for the Request
+pub fn identity() -> impl Filter<Extract = (Identity,), Error = Rejection> + Clone {
+    warp::path::full()
+        .and(warp::method())
+        .and(warp::cookie::optional::<String>(SESSION_COOKIE))
+        .and(warp::header::optional::<String>("authorization"))
+        .and_then(
+            |path: FullPath, method: Method, session, authorization| async move {
+                let identity = identify(session, authorization)
+                    .await
+                    .map_err(warp::reject::custom)?
+                    .ok_or_else(|| warp::reject::custom(Unauthorized))?;
+                if !identity.allows(required_scope(&method, path.as_str())) {
+                    return Err(warp::reject::custom(Forbidden));
+                }
+
+                Ok(identity)
+            },
+        )
+}
+
+/// Rejects the Request like [`identity`], for Routes that do not need to know the User
+pub fn authenticated() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    identity().map(|_| ()).untuple_one()
+}
+
+/// Turns the Rejections of the Authentication into the matching Responses
+pub async fn handle_rejection(rejection: Rejection) -> Result<impl Reply, Rejection> {
+    if rejection.find::<Unauthorized>().is_some() {
+        Ok(warp::reply::with_status(
+            "Unauthorized".to_string(),
+            StatusCode::UNAUTHORIZED,
+        ))
+    } else if rejection.find::<Forbidden>().is_some() {
+        Ok(warp::reply::with_status(
+            "Forbidden".to_string(),
+            StatusCode::FORBIDDEN,
+        ))
+    } else if let Some(e) = rejection.find::<StorageError>() {
+        // Looking up the Identity of the Request failed
+        Ok(e.reply())
+    } else {
+        Err(rejection)
+    }
+}
+
+/// Creates the first Admin if there are no Users yet, so that the API can be used at all.
+///
+/// The Password is written to a File in the Workspace that only the current User can read, instead
+/// of ending up in the Logs
+pub async fn bootstrap(workspace: &Path) -> Result<(), String> {
+    let store = &STATE.get().unwrap().store;
+    if !store.load_users().await?.is_empty() {
+        return Ok(());
+    }
+
+    let password = generate_token();
+    let path = workspace.join(ADMIN_PASSWORD_FILE);
+    write_secret(&path, &password)
+        .map_err(|e| format!("Writing the Password to {:?}: {}", path, e))?;
+
+    store
+        .store_user(User {
+            name: "admin".to_string(),
+            password_hash: hash_password(&password),
+            admin: true,
+        })
+        .await?;
+
+    println!(
+        "Created User \"admin\", the Password is stored in {:?}",
+        path
+    );
+    Ok(())
+}
+
+/// Writes the Secret to a new File that is only accessible by the current User
+fn write_secret(path: &Path, secret: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // A leftover File could be readable by others, so it is replaced instead of truncated
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(secret.as_bytes())
+}
+
+#[derive(Debug, Deserialize)]
+struct Login {
+    name: String,
+    password: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct NewUser {
+    name: String,
+    password: String,
+    #[serde(default)]
+    admin: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct NewToken {
+    name: String,
+    scopes: Vec<Scope>,
+}
+
+#[derive(Debug, Serialize)]
+struct CreatedToken {
+    name: String,
+    /// The Token itself, which is only ever shown once
+    token: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Me {
+    name: String,
+    admin: bool,
+}
+
+/// The HTTP-Routes for logging in and managing the Users and API-Tokens
+pub fn routes() -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let login = warp::path!("api" / "auth" / "login")
+        .and(warp::post())
+        .and(warp::body::json())
+        .then(|login: Login| async move {
+            let store = &STATE.get().unwrap().store;
+
+            let valid = match store.load_user(login.name.clone()).await {
+                Ok(Some(user)) => verify_password(&login.password, &user.password_hash),
+                Ok(None) => false,
+                Err(e) => return e.reply().into_response(),
+            };
+            if !valid {
+                return warp::reply::with_status("Invalid Login", StatusCode::UNAUTHORIZED)
+                    .into_response();
+            }
+
+            let token = generate_token();
+            let stored = store
+                .store_session(Session {
+                    token_hash: hash_token(&token),
+                    user: login.name,
+                    expires: now() + SESSION_DURATION,
+                })
+                .await;
+            if let Err(e) = stored {
+                return e.reply().into_response();
+            }
+
+            let cookie = format!(
+                "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}",
+                SESSION_COOKIE, token, SESSION_DURATION
+            );
+            warp::reply::with_header("", header::SET_COOKIE, cookie).into_response()
+        });
+
+    let logout = warp::path!("api" / "auth" / "logout")
+        .and(warp::post())
+        .and(authenticated())
+        .and(warp::cookie::optional::<String>(SESSION_COOKIE))
+        .then(|session: Option<String>| async move {
+            if let Some(session) = session {
+                let store = &STATE.get().unwrap().store;
+                if let Err(e) = store.remove_session(hash_token(&session)).await {
+                    return e.reply().into_response();
+                }
+            }
+
+            let cookie = format!(
+                "{}=; Path=/; HttpOnly; SameSite=Lax; Max-Age=0",
+                SESSION_COOKIE
+            );
+            warp::reply::with_header("", header::SET_COOKIE, cookie).into_response()
+        });
+
+    let me = warp::path!("api" / "auth" / "me")
+        .and(warp::get())
+        .and(identity())
+        .map(|identity: Identity| {
+            warp::reply::json(&Me {
+                name: identity.user,
+                admin: identity.admin,
+            })
+        });
+
+    let add_user = warp::path!("api" / "users" / "add")
+        .and(warp::post())
+        .and(identity())
+        .and(warp::body::json())
+        .then(|identity: Identity, user: NewUser| async move {
+            if !identity.admin {
+                return warp::reply::with_status("Forbidden".to_string(), StatusCode::FORBIDDEN);
+            }
+            if user.name.is_empty() || user.password.is_empty() {
+                return warp::reply::with_status(
+                    "Missing name or password".to_string(),
+                    StatusCode::BAD_REQUEST,
+                );
+            }
+
+            let store = &STATE.get().unwrap().store;
+            let stored = store
+                .store_user(User {
+                    password_hash: hash_password(&user.password),
+                    name: user.name,
+                    admin: user.admin,
+                })
+                .await;
+            if let Err(e) = stored {
+                return e.reply();
+            }
+
+            warp::reply::with_status(String::new(), StatusCode::OK)
+        });
+
+    let list_tokens = warp::path!("api" / "tokens" / "list")
+        .and(warp::get())
+        .and(identity())
+        .then(|identity: Identity| async move {
+            let store = &STATE.get().unwrap().store;
+            match store.load_tokens(identity.user).await {
+                Ok(tokens) => warp::reply::json(&tokens).into_response(),
+                Err(e) => e.reply().into_response(),
+            }
+        });
+
+    let add_token = warp::path!("api" / "tokens" / "add")
+        .and(warp::post())
+        .and(identity())
+        .and(warp::body::json())
+        .then(|identity: Identity, new: NewToken| async move {
+            // A Token can not be used to create a more powerful Token and as Agents receive the
+            // Targets of every Project, only Admins can create Tokens for them
+            let allowed = |scope: &Scope| match (scope, &identity.scopes) {
+                (Scope::Agent, None) => identity.admin,
+                (Scope::Agent, Some(scopes)) => identity.admin && scopes.contains(scope),
+                _ => identity.allows(*scope),
+            };
+            if !new.scopes.iter().all(allowed) {
+                return warp::reply::with_status("Forbidden", StatusCode::FORBIDDEN)
+                    .into_response();
+            }
+
+            let token = generate_token();
+            let store = &STATE.get().unwrap().store;
+            let stored = store
+                .store_token(ApiToken {
+                    user: identity.user,
+                    name: new.name.clone(),
+                    token_hash: hash_token(&token),
+                    scopes: new.scopes,
+                    created: now(),
+                })
+                .await;
+            if let Err(e) = stored {
+                return e.reply().into_response();
+            }
+
+            warp::reply::json(&CreatedToken {
+                name: new.name,
+                token,
+            })
+            .into_response()
+        });
+
+    let remove_token = warp::path!("api" / "tokens" / "remove")
+        .and(warp::post())
+        .and(identity())
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .then(
+            |identity: Identity, query: std::collections::HashMap<String, String>| async move {
+                let name = match query.get("name") {
+                    Some(n) => n.to_string(),
+                    None => {
+                        return warp::reply::with_status(
+                            "Missing name".to_string(),
+                            StatusCode::BAD_REQUEST,
+                        )
+                    }
+                };
+
+                let store = &STATE.get().unwrap().store;
+                if let Err(e) = store.remove_token(identity.user, name).await {
+                    return e.reply();
+                }
+
+                warp::reply::with_status(String::new(), StatusCode::OK)
+            },
+        );
+
+    login
+        .or(logout)
+        .or(me)
+        .or(add_user)
+        .or(list_tokens)
+        .or(add_token)
+        .or(remove_token)
+}
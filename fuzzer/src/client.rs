@@ -0,0 +1,361 @@
+//! The Client used by the CLI-Subcommands
+//!
+//! The Client either talks to a running Server using its HTTP-API or, if no Server is configured,
+//! works directly on the configured Storage-Backend, which requires the global [`STATE`] to be
+//! set up.
+
+use std::{path::Path, sync::Arc};
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    archive::{Conflict, ImportReport},
+    project::{Project, Source, Target},
+    runner::{process::Launcher, Runner},
+    runs::RunRecord,
+    storage::blobs,
+    FuzzResult, RunRequest, STATE,
+};
+
+/// A Client for managing the Projects
+pub enum Client {
+    /// Uses the HTTP-API of the Server at the given URL
+    Remote {
+        client: reqwest::Client,
+        server: String,
+    },
+    /// Uses the Storage-Backend directly
+    Local,
+}
+
+impl Client {
+    /// Creates a Client for the Server, authenticated using the API-Token
+    pub fn remote(server: String, token: Option<String>) -> Result<Self, String> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Some(token) = token {
+            let value = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(|_| "Invalid API-Token".to_string())?;
+            headers.insert(reqwest::header::AUTHORIZATION, value);
+        }
+
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self::Remote {
+            client,
+            server: server.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Sends the Request and turns every unsuccessful Response into an Error with its Body
+    async fn send(request: reqwest::RequestBuilder) -> Result<reqwest::Response, String> {
+        let response = request.send().await.map_err(|e| e.to_string())?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("{}: {}", status, body));
+        }
+
+        Ok(response)
+    }
+
+    async fn get<T>(
+        client: &reqwest::Client,
+        url: String,
+        query: &[(&str, &str)],
+    ) -> Result<T, String>
+    where
+        T: DeserializeOwned,
+    {
+        Self::send(client.get(url).query(query))
+            .await?
+            .json()
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn list_projects(&self) -> Result<Vec<Project>, String> {
+        match self {
+            Self::Remote { client, server } => {
+                Self::get(client, format!("{}/api/projects/list", server), &[]).await
+            }
+            Self::Local => Ok(STATE.get().unwrap().store.load_projects().await?),
+        }
+    }
+
+    pub async fn add_project(&self, name: String, repo: String) -> Result<(), String> {
+        let project = Project {
+            name,
+            source: Source::Git { repo },
+            targets: Vec::new(),
+        };
+
+        match self {
+            Self::Remote { client, server } => {
+                let url = format!("{}/api/projects/update", server);
+                Self::send(client.post(url).json(&project)).await?;
+            }
+            Self::Local => STATE.get().unwrap().store.update_project(project).await?,
+        };
+
+        Ok(())
+    }
+
+    pub async fn remove_project(&self, name: String) -> Result<(), String> {
+        match self {
+            Self::Remote { client, server } => {
+                let url = format!("{}/api/projects/remove", server);
+                Self::send(client.post(url).query(&[("pname", &name)])).await?;
+            }
+            Self::Local => STATE.get().unwrap().store.remove_project(name).await?,
+        };
+
+        Ok(())
+    }
+
+    pub async fn add_target(&self, project: String, target: Target) -> Result<(), String> {
+        target.validate()?;
+
+        match self {
+            Self::Remote { client, server } => {
+                let url = format!("{}/api/projects/targets/add", server);
+                Self::send(client.post(url).query(&[("pname", &project)]).json(&target)).await?;
+            }
+            Self::Local => {
+                let store = &STATE.get().unwrap().store;
+                if store.load_project(&project).await?.is_none() {
+                    return Err(format!("Unknown Project {:?}", project));
+                }
+
+                store.add_project_target(project, target).await?;
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Updates an existing Target of the Project, which fails if there is no Target with its Name
+    pub async fn update_target(&self, project: String, target: Target) -> Result<(), String> {
+        target.validate()?;
+
+        match self {
+            Self::Remote { client, server } => {
+                let url = format!("{}/api/projects/targets/update", server);
+                Self::send(client.post(url).query(&[("pname", &project)]).json(&target)).await?;
+            }
+            Self::Local => {
+                let store = &STATE.get().unwrap().store;
+                if store
+                    .load_target(project.clone(), target.name.clone())
+                    .await?
+                    .is_none()
+                {
+                    return Err(format!("Unknown Target {:?} in {:?}", target.name, project));
+                }
+
+                store.add_project_target(project, target).await?;
+            }
+        };
+
+        Ok(())
+    }
+
+    pub async fn target(&self, project: String, name: String) -> Result<Target, String> {
+        match self {
+            Self::Remote { client, server } => {
+                let url = format!("{}/api/projects/targets", server);
+                Self::get(client, url, &[("pname", &project), ("name", &name)]).await
+            }
+            Self::Local => {
+                let store = &STATE.get().unwrap().store;
+                store
+                    .load_target(project.clone(), name.clone())
+                    .await?
+                    .ok_or_else(|| format!("Unknown Target {:?} in {:?}", name, project))
+            }
+        }
+    }
+
+    pub async fn remove_target(&self, project: String, name: String) -> Result<(), String> {
+        match self {
+            Self::Remote { client, server } => {
+                let url = format!("{}/api/projects/targets/remove", server);
+                let query = [("pname", &project), ("name", &name)];
+                Self::send(client.post(url).query(&query)).await?;
+            }
+            Self::Local => {
+                let store = &STATE.get().unwrap().store;
+                store.remove_project_target(project, name).await?;
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Discovers the cargo-fuzz Targets of the Project and optionally adds the new ones, a local
+    /// Client checks the Project out into the Workspace
+    pub async fn discover(
+        &self,
+        project: String,
+        create: bool,
+        workspace: &Path,
+        launcher: &(dyn Launcher + Sync),
+    ) -> Result<Vec<Target>, String> {
+        match self {
+            Self::Remote { client, server } => {
+                let url = format!("{}/api/projects/discover", server);
+                let query = [("pname", project.as_str()), ("create", &create.to_string())];
+                Self::send(client.post(url).query(&query))
+                    .await?
+                    .json()
+                    .await
+                    .map_err(|e| e.to_string())
+            }
+            Self::Local => {
+                let store = &STATE.get().unwrap().store;
+                let stored = store
+                    .load_project(&project)
+                    .await?
+                    .ok_or_else(|| format!("Unknown Project {:?}", project))?;
+
+                let targets = crate::discovery::discover_source(
+                    workspace,
+                    &project,
+                    &stored.source,
+                    launcher,
+                )
+                .await?;
+                if create {
+                    crate::discovery::create_missing(&stored, &targets).await?;
+                }
+
+                Ok(targets)
+            }
+        }
+    }
+
+    /// Starts the Target, which only returns once the Run is finished when running locally
+    pub async fn run<R>(
+        &self,
+        project: String,
+        target: String,
+        runner: Arc<R>,
+    ) -> Result<(), String>
+    where
+        R: Runner + Send + Sync + 'static,
+    {
+        let request = RunRequest {
+            pname: project,
+            name: target,
+        };
+
+        match self {
+            Self::Remote { client, server } => {
+                let url = format!("{}/api/run", server);
+                Self::send(client.post(url).json(&request)).await?;
+            }
+            Self::Local => {
+                let store = &STATE.get().unwrap().store;
+                let project = store
+                    .load_project(&request.pname)
+                    .await?
+                    .ok_or_else(|| format!("Unknown Project {:?}", request.pname))?;
+                let target = project
+                    .targets
+                    .into_iter()
+                    .find(|t| t.name == request.name)
+                    .ok_or_else(|| format!("Unknown Target {:?}", request.name))?;
+
+                crate::run(request, runner, target, project.source).await;
+            }
+        };
+
+        Ok(())
+    }
+
+    /// The recent Runs, which are only known to a running Server
+    pub async fn runs(&self, project: Option<String>) -> Result<Vec<RunRecord>, String> {
+        match self {
+            Self::Remote { client, server } => {
+                let url = format!("{}/api/runs", server);
+                match project {
+                    Some(p) => Self::get(client, url, &[("pname", &p)]).await,
+                    None => Self::get(client, url, &[]).await,
+                }
+            }
+            Self::Local => Err("The Runs are only known to a running Server".to_string()),
+        }
+    }
+
+    pub async fn results(&self, project: String) -> Result<Vec<FuzzResult>, String> {
+        match self {
+            Self::Remote { client, server } => {
+                let url = format!("{}/api/results", server);
+                Self::get(client, url, &[("pname", &project)]).await
+            }
+            Self::Local => Ok(STATE.get().unwrap().store.load_results(project).await?),
+        }
+    }
+
+    /// Exports the Projects, or all of them if no Names are given, as an Archive
+    pub async fn export(&self, projects: &[String], workspace: &Path) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Remote { client, server } => {
+                let url = format!("{}/api/export", server);
+                let projects = projects.join(",");
+                Self::send(client.get(url).query(&[("projects", projects)]))
+                    .await?
+                    .bytes()
+                    .await
+                    .map(|b| b.to_vec())
+                    .map_err(|e| e.to_string())
+            }
+            Self::Local => crate::archive::export(workspace, projects).await,
+        }
+    }
+
+    /// Restores the Projects of the Archive
+    pub async fn import(
+        &self,
+        archive: Vec<u8>,
+        conflict: Conflict,
+        workspace: &Path,
+    ) -> Result<ImportReport, String> {
+        match self {
+            Self::Remote { client, server } => {
+                let url = format!("{}/api/import", server);
+                let query = [("conflict", conflict.as_str())];
+                Self::send(client.post(url).query(&query).body(archive))
+                    .await?
+                    .json()
+                    .await
+                    .map_err(|e| e.to_string())
+            }
+            Self::Local => crate::archive::import(workspace, &archive, conflict).await,
+        }
+    }
+
+    /// Stores all the Results of the Project in a Folder per Target, named after the Hash of
+    /// their Content, and returns the Number of Results
+    pub async fn download_results(&self, project: String, dir: &Path) -> Result<usize, String> {
+        let results = self.results(project).await?;
+
+        for result in results.iter() {
+            let folder = match result.sanitizer {
+                Some(s) => dir.join(format!("{}-{}", result.name, s.as_str())),
+                None => dir.join(&result.name),
+            };
+            std::fs::create_dir_all(&folder)
+                .map_err(|e| format!("Creating {}: {}", folder.display(), e))?;
+
+            let path = folder.join(format!("crash-{}", blobs::hash(&result.content)));
+            std::fs::write(&path, &result.content)
+                .map_err(|e| format!("Writing {}: {}", path.display(), e))?;
+        }
+
+        Ok(results.len())
+    }
+}
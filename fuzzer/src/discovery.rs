@@ -0,0 +1,177 @@
+//! Discovers the cargo-fuzz Targets of a Project
+//!
+//! The Targets are found by parsing the `[[bin]]` Entries of every `fuzz/Cargo.toml` in the
+//! Repository, instead of running `cargo fuzz list`, so that no Code of the Project is executed.
+
+use std::{
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{
+    project::{CargoFuzzOptions, Limits, Project, RunTarget, Source, Target},
+    runner::process::{Launcher, Phase},
+    storage::StorageError,
+    STATE,
+};
+
+/// Folders that never contain the Sources of a Project
+const SKIPPED_FOLDERS: &[&str] = &["target", "node_modules"];
+
+/// Makes sure that concurrent Discoveries of the same Project use different Checkouts
+static CHECKOUTS: AtomicU64 = AtomicU64::new(0);
+
+/// Checks out the Source into the Workspace, using the same Launcher as the Runs, and discovers
+/// the Targets in it
+pub async fn discover_source(
+    workspace: &Path,
+    pname: &str,
+    source: &Source,
+    launcher: &(dyn Launcher + Sync),
+) -> Result<Vec<Target>, String> {
+    let checkout = workspace.join(pname).join(format!(
+        ".discover-{}",
+        CHECKOUTS.fetch_add(1, Ordering::Relaxed)
+    ));
+    if checkout.exists() {
+        let _ = std::fs::remove_dir_all(&checkout);
+    }
+    let project_path = workspace.join(pname);
+    std::fs::create_dir_all(&project_path).map_err(|e| e.to_string())?;
+
+    let cmd = match source {
+        Source::Git { repo } => {
+            let mut cmd = std::process::Command::new("git");
+            cmd.arg("clone")
+                .args(["--depth", "1"])
+                .arg(repo)
+                .arg(&checkout);
+            cmd
+        }
+    };
+    let result = match launcher.launch(Phase::Checkout, &[&project_path], cmd) {
+        Ok(cmd) => tokio::process::Command::from(cmd).output().await,
+        Err(e) => Err(e),
+    };
+
+    let targets = match result {
+        Ok(output) if output.status.success() => {
+            let checkout = checkout.clone();
+            tokio::task::spawn_blocking(move || discover(&checkout))
+                .await
+                .map_err(|e| e.to_string())
+        }
+        Ok(output) => Err(format!(
+            "Cloning failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+        Err(e) => Err(format!("Cloning failed: {}", e)),
+    };
+
+    let _ = std::fs::remove_dir_all(&checkout);
+
+    targets
+}
+
+/// Adds the discovered Targets that are not part of the Project yet, so that the Options of the
+/// existing Targets are kept
+pub async fn create_missing(project: &Project, targets: &[Target]) -> Result<(), StorageError> {
+    let store = &STATE.get().unwrap().store;
+
+    for target in targets.iter() {
+        // The Targets are derived from the Repository, which can contain anything
+        if let Err(e) = target.validate() {
+            println!("Skipping discovered Target {}: {}", target.name, e);
+            continue;
+        }
+
+        if !project.targets.iter().any(|t| t.name == target.name) {
+            store
+                .add_project_target(project.name.clone(), target.clone())
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds all the cargo-fuzz Targets in the Repository
+pub fn discover(repo: &Path) -> Vec<Target> {
+    let mut targets: Vec<Target> = Vec::new();
+    find_fuzz_folders(repo, repo, &mut |folder, bins| {
+        for bin in bins {
+            // Targets in different Folders might use the same Name
+            let name = if targets.iter().any(|t| t.name == bin) {
+                format!("{}-{}", folder.replace('/', "-"), bin)
+            } else {
+                bin.clone()
+            };
+
+            targets.push(Target {
+                name,
+                folder: folder.to_string(),
+                target: RunTarget::CargoFuzz {
+                    name: bin,
+                    options: CargoFuzzOptions::default(),
+                },
+                repeating: false,
+                sanitizers: Vec::new(),
+                workers: 1,
+                limits: Limits::default(),
+                budget_secs: None,
+                interval_secs: None,
+            });
+        }
+    });
+
+    targets
+}
+
+/// Walks the Repository and calls the Callback with the Folder, relative to the Repository, and
+/// the Fuzz-Targets for every cargo-fuzz Project
+fn find_fuzz_folders(repo: &Path, dir: &Path, found: &mut dyn FnMut(&str, Vec<String>)) {
+    let fuzz_manifest = dir.join("fuzz").join("Cargo.toml");
+    if let Some(bins) = fuzz_bins(&fuzz_manifest) {
+        let folder = match dir.strip_prefix(repo) {
+            Ok(p) if p.as_os_str().is_empty() => ".".to_string(),
+            Ok(p) => p.to_string_lossy().to_string(),
+            Err(_) => return,
+        };
+        found(&folder, bins);
+    }
+
+    let mut entries: Vec<_> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).collect(),
+        Err(_) => return,
+    };
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') || name == "fuzz" || SKIPPED_FOLDERS.contains(&name.as_ref()) {
+            continue;
+        }
+
+        // Symlinks are not followed, as they could point outside of the Repository
+        if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+            find_fuzz_folders(repo, &entry.path(), found);
+        }
+    }
+}
+
+/// The Names of all the Binaries of the cargo-fuzz Project
+fn fuzz_bins(manifest: &Path) -> Option<Vec<String>> {
+    let content = std::fs::read_to_string(manifest).ok()?;
+    let manifest: toml::Value = toml::from_str(&content).ok()?;
+
+    let bins = manifest
+        .get("bin")?
+        .as_array()?
+        .iter()
+        .filter_map(|bin| bin.get("name")?.as_str())
+        .map(|name| name.to_string())
+        .collect();
+
+    Some(bins)
+}
@@ -1,82 +1,189 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
-use project::{Source, Target};
+use project::{Sanitizer, Source, Target};
 use serde::{Deserialize, Serialize};
-use tokio::sync::OnceCell;
+use tokio::sync::{mpsc, OnceCell, Semaphore};
 
 mod target;
 pub use target::FuzzTarget;
 
+pub mod agent;
+pub mod archive;
+pub mod auth;
+pub mod client;
+pub mod config;
+pub mod discovery;
+pub mod manifest;
 pub mod project;
 
 pub mod runner;
+pub mod runs;
 pub mod storage;
+pub mod updates;
 
 #[derive(Debug)]
 pub struct State {
-    pub running: Mutex<HashSet<String>>,
+    /// The Runs that are currently running, as the Project and the Name of the Run
+    pub running: Mutex<HashSet<(String, String)>>,
+    /// The IDs of all the Processes that belong to a Run, keyed by `<project>/<run>`
+    pub processes: Mutex<HashMap<String, Vec<u32>>>,
+    /// The Records of the recent Runs
+    pub runs: Mutex<runs::Runs>,
+    /// Receives the Updates about all the Runs
+    pub updates: updates::Updates,
+    /// Limits the Number of Runs at the same Time, if there is a Limit
+    pub scheduler: Option<Semaphore>,
     pub store: storage::StorageHandle,
 }
 
 pub static STATE: OnceCell<State> = OnceCell::const_new();
 
-#[derive(Debug, Serialize)]
+/// The current Time in Seconds since the Unix-Epoch
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FuzzResult {
     name: String,
     content: Vec<u8>,
+    /// The Sanitizer of the Run that found this Result
+    sanitizer: Option<Sanitizer>,
+    /// The Hash of the Content, if the Content is stored in the Blob-Store instead of the Storage
+    #[serde(skip)]
+    blob: Option<String>,
+}
+
+impl FuzzResult {
+    pub fn new(name: String, content: Vec<u8>, sanitizer: Option<Sanitizer>) -> Self {
+        Self {
+            name,
+            content,
+            sanitizer,
+            blob: None,
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct RunRequest {
     pub pname: String,
     pub name: String,
 }
 
+/// Runs the given Target, which results in one Run for every Sanitizer configured for the Target.
+///
+/// All the Runs of a Target share the same Corpus
 pub async fn run<R>(req: RunRequest, runner: Arc<R>, target: Target, source: Source)
 where
     R: runner::Runner + Send + Sync + 'static,
 {
-    let pname = req.pname.clone();
-    let name = req.name.clone();
+    let runs: Vec<_> = target
+        .matrix()
+        .into_iter()
+        .map(|(sanitizer, target)| {
+            tokio::spawn(run_single(
+                req.pname.clone(),
+                req.name.clone(),
+                sanitizer,
+                runner.clone(),
+                target,
+                source.clone(),
+            ))
+        })
+        .collect();
+
+    for run in runs {
+        let _ = run.await;
+    }
+}
 
-    loop {
-        let running_req = RunRequest {
-            pname: req.pname.clone(),
-            name: req.name.clone(),
-        };
+async fn run_single<R>(
+    pname: String,
+    name: String,
+    sanitizer: Option<Sanitizer>,
+    runner: Arc<R>,
+    target: Target,
+    source: Source,
+) where
+    R: runner::Runner + Send + Sync + 'static,
+{
+    // Every Run of the Matrix needs its own Name, as they would otherwise share the same checkout
+    let run_name = match sanitizer {
+        Some(s) => format!("{}-{}", name, s.as_str()),
+        None => name.clone(),
+    };
 
+    loop {
         let ftarget = FuzzTarget::new(
-            running_req.pname,
-            running_req.name,
+            pname.clone(),
+            run_name.clone(),
             target.clone(),
             source.clone(),
         );
         let runner = runner.clone();
 
-        {
+        // The Permit is held until the Run is finished
+        let permit = match &STATE.get().unwrap().scheduler {
+            Some(scheduler) => Some(scheduler.acquire().await),
+            None => None,
+        };
+
+        let run_id = {
             let state = STATE.get().unwrap();
-            let mut running = state.running.try_lock().unwrap();
-            running.insert(name.clone());
-        }
+            let mut running = state.running.lock().unwrap();
+            running.insert((pname.clone(), run_name.clone()));
+
+            let run_id = state.runs.lock().unwrap().start(&pname, &name, sanitizer);
+            state.updates.send(updates::Update::RunStarted {
+                run: run_id,
+                project: pname.clone(),
+                target: name.clone(),
+                sanitizer,
+            });
+
+            run_id
+        };
+
+        let (events, recv) = mpsc::unbounded_channel();
+        let recorder = tokio::spawn(record_events(run_id, recv));
 
-        match crate::runner::run_completion(runner.clone(), ftarget).await {
+        let output = match target.budget_secs {
+            Some(budget) => {
+                let budget = std::time::Duration::from_secs(budget);
+                crate::runner::run_timeout(runner.clone(), ftarget, events, budget).await
+            }
+            None => crate::runner::run_completion(runner.clone(), ftarget, events).await,
+        };
+        let _ = recorder.await;
+        STATE
+            .get()
+            .unwrap()
+            .runs
+            .lock()
+            .unwrap()
+            .finish(run_id, output.as_ref());
+
+        match output {
             Some(r) => {
                 let state = STATE.get().unwrap();
 
-                for res in r {
-                    state
-                        .store
-                        .store_result(
-                            pname.clone(),
-                            FuzzResult {
-                                name: name.clone(),
-                                content: res,
-                            },
-                        )
-                        .await;
+                if let Some(limit) = r.exceeded {
+                    println!("Run exceeded the {:?} Limit", limit);
+                }
+
+                for res in r.artifacts {
+                    let result = FuzzResult::new(name.clone(), res, sanitizer);
+                    if let Err(e) = state.store.store_result(pname.clone(), result).await {
+                        println!("Storing Result: {}", e);
+                    }
                 }
             }
             None => {
@@ -87,10 +194,28 @@ where
         if !target.repeating {
             break;
         }
+
+        if let Some(interval) = target.interval_secs {
+            // Other Runs can use the Slot while this Target is waiting
+            drop(permit);
+            tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+        }
     }
 
     let state = STATE.get().unwrap();
-    let mut running = state.running.try_lock().unwrap();
+    let mut running = state.running.lock().unwrap();
+
+    running.remove(&(pname, run_name));
+}
 
-    running.remove(&name);
+/// Records all the Events of the Run and passes them on to the Subscribers
+async fn record_events(run: u64, mut events: mpsc::UnboundedReceiver<runner::Event>) {
+    while let Some(event) = events.recv().await {
+        let state = STATE.get().unwrap();
+
+        let event = state.runs.lock().unwrap().event(run, event);
+        if let Some(event) = event {
+            state.updates.send(updates::Update::Run(event));
+        }
+    }
 }
@@ -1,163 +1,1056 @@
 use std::{
     collections::{HashMap, HashSet},
+    io::Read,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
 };
 
 use cfuzz::{
-    project::{Project, Target},
-    run, runner, storage, FuzzResult, RunRequest, State, STATE,
+    archive::{self, Conflict},
+    auth::{self, Identity, Member, Role},
+    client::Client,
+    config::{
+        BlobConfig, Cli, Command, Config, ProjectCommand, ResultsCommand, RunnerConfig,
+        StorageConfig, TargetCommand,
+    },
+    discovery,
+    project::{Project, Source, Target},
+    run,
+    runner::{
+        self,
+        container::ContainerRunner,
+        process::{Direct, Launcher, ProcessRunner},
+        remote::{self, Coordinator, RemoteRunner},
+        sandbox::{Bubblewrap, SandboxRunner},
+    },
+    runs::{RunRecord, Runs},
+    storage::{
+        self,
+        blobs::{
+            s3::{Credentials, S3BlobStore},
+            BlobStore, FsBlobStore,
+        },
+        postgres::PostgresBackend,
+    },
+    updates::Updates,
+    FuzzResult, RunRequest, State, STATE,
 };
-use warp::Filter;
+use clap::Parser;
+use futures_util::StreamExt;
+use tokio::sync::Semaphore;
+use warp::{hyper::StatusCode, Filter, Reply};
+
+/// The largest Archive that can be imported through the API
+const MAX_ARCHIVE_SIZE: u64 = 1024 * 1024 * 1024;
 
 #[tokio::main]
 async fn main() {
-    let storage_handle = cfuzz::storage::start(storage::sqlite::SqliteBackend::new("./data.db"));
+    let cli = Cli::parse();
+    let config = match Config::load(&cli) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Invalid Configuration: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let coordinator = Arc::new(Coordinator::new());
+    let runner = Arc::new(create_runner(&config, cli.config.as_deref(), &coordinator));
+    let launcher = create_launcher(&config, cli.config.as_deref());
+
+    match cli.command {
+        Some(Command::Agent {
+            coordinator,
+            name,
+            slots,
+        }) => {
+            if config.runner == RunnerConfig::Remote {
+                eprintln!("Invalid Configuration: Agents can not use the remote Runner");
+                std::process::exit(1);
+            }
+
+            let name = name.unwrap_or_else(|| {
+                std::fs::read_to_string("/etc/hostname")
+                    .map(|h| h.trim().to_string())
+                    .unwrap_or_else(|_| "agent".to_string())
+            });
 
-    let runner = Arc::new(runner::process::ProcessRunner::new("./fuzzing"));
+            cfuzz::agent::run(coordinator, name, slots, cli.token, runner).await;
+        }
+        Some(Command::Serve) | Some(Command::Coordinator) | None => {
+            serve(config, runner, launcher, coordinator).await;
+        }
+        Some(command) => {
+            let client = match cli.server {
+                Some(server) => Client::remote(server, cli.token),
+                None => {
+                    setup_state(&config).await;
+                    Ok(Client::Local)
+                }
+            };
+
+            let result = match client {
+                Ok(client) => {
+                    client_command(&config, client, command, runner, launcher.as_ref()).await
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    };
+}
+
+/// Reads the JSON Definition of a Target from the File, `-` reads it from stdin
+fn read_target(file: &std::path::Path) -> Result<Target, String> {
+    let mut content = String::new();
+    let read = if file.as_os_str() == "-" {
+        std::io::stdin().read_to_string(&mut content)
+    } else {
+        std::fs::File::open(file).and_then(|mut f| f.read_to_string(&mut content))
+    };
+    read.map_err(|e| format!("Reading {}: {}", file.display(), e))?;
+
+    serde_json::from_str(&content).map_err(|e| format!("Parsing {}: {}", file.display(), e))
+}
+
+/// Runs one of the Client-Commands
+async fn client_command<R>(
+    config: &Config,
+    client: Client,
+    command: Command,
+    runner: Arc<R>,
+    launcher: &(dyn Launcher + Sync),
+) -> Result<(), String>
+where
+    R: runner::Runner + Send + Sync + 'static,
+{
+    match command {
+        Command::Project(ProjectCommand::Add { name, repo }) => {
+            client.add_project(name, repo).await?;
+        }
+        Command::Project(ProjectCommand::List) => {
+            for project in client.list_projects().await? {
+                let Source::Git { repo } = &project.source;
+                println!("{}\t{}", project.name, repo);
+
+                for target in project.targets.iter() {
+                    println!("  {}\t{}", target.name, target.folder);
+                }
+            }
+        }
+        Command::Project(ProjectCommand::Remove { name }) => {
+            client.remove_project(name).await?;
+        }
+        Command::Target(TargetCommand::Add { project, file }) => {
+            client.add_target(project, read_target(&file)?).await?;
+        }
+        Command::Target(TargetCommand::Update { project, file }) => {
+            client.update_target(project, read_target(&file)?).await?;
+        }
+        Command::Target(TargetCommand::Show { project, name }) => {
+            let target = client.target(project, name).await?;
+            println!("{}", serde_json::to_string_pretty(&target).unwrap());
+        }
+        Command::Target(TargetCommand::Remove { project, name }) => {
+            client.remove_target(project, name).await?;
+        }
+        Command::Target(TargetCommand::Discover { project, create }) => {
+            for target in client
+                .discover(project, create, &config.workspace, launcher)
+                .await?
+            {
+                println!("{}\t{}", target.name, target.folder);
+            }
+        }
+        Command::Run { project, target } => {
+            if matches!(client, Client::Local) && config.runner == RunnerConfig::Remote {
+                return Err("Running locally needs a local Runner".to_string());
+            }
+
+            client.run(project, target, runner).await?;
+        }
+        Command::Runs { project } => {
+            for run in client.runs(project).await? {
+                let sanitizer = run.sanitizer.map(|s| s.as_str()).unwrap_or("-");
+                println!(
+                    "{}\t{}\t{}\t{}\t{:?}\t{}",
+                    run.id, run.project, run.target, sanitizer, run.status, run.crashes
+                );
+            }
+        }
+        Command::Results(ResultsCommand::Download { project, output }) => {
+            let count = client.download_results(project, &output).await?;
+            println!("Downloaded {} Results to {}", count, output.display());
+        }
+        Command::Export { output, project } => {
+            let archive = client.export(&project, &config.workspace).await?;
+            std::fs::write(&output, archive)
+                .map_err(|e| format!("Writing {}: {}", output.display(), e))?;
+        }
+        Command::Import { file, conflict } => {
+            let archive =
+                std::fs::read(&file).map_err(|e| format!("Reading {}: {}", file.display(), e))?;
+
+            let report = client.import(archive, conflict, &config.workspace).await?;
+            for name in report.imported.iter() {
+                println!("Imported {}", name);
+            }
+            for name in report.skipped.iter() {
+                println!("Skipped {}", name);
+            }
+            println!(
+                "Imported {} Results and {} Corpus-Entries",
+                report.results, report.corpus
+            );
+        }
+        Command::Serve | Command::Coordinator | Command::Agent { .. } => unreachable!(),
+    };
+
+    Ok(())
+}
+
+/// Sets up the global State using the configured Storage
+async fn setup_state(config: &Config) {
+    let updates = Updates::new();
+    let mut storage_handle = match &config.storage {
+        StorageConfig::Sqlite { path } => match storage::sqlite::SqliteBackend::new(path) {
+            Ok(backend) => storage::start(backend),
+            Err(e) => {
+                eprintln!("Opening the SQLite Database: {}", e);
+                std::process::exit(1);
+            }
+        },
+        StorageConfig::Memory => storage::start(storage::memory::InMemoryBackend::new()),
+        StorageConfig::Postgres {
+            url,
+            max_connections,
+        } => match PostgresBackend::connect(url, *max_connections).await {
+            Ok(backend) => storage::start(backend),
+            Err(e) => {
+                eprintln!("Connecting to PostgreSQL: {}", e);
+                std::process::exit(1);
+            }
+        },
+    }
+    .with_updates(updates.clone());
+    if let Some(blobs) = &config.blobs {
+        match create_blobs(blobs) {
+            Ok(blobs) => storage_handle = storage_handle.with_blobs(blobs),
+            Err(e) => {
+                eprintln!("Setting up the Blob-Store: {}", e);
+                std::process::exit(1);
+            }
+        };
+    }
 
     STATE
         .set(State {
             running: Mutex::new(HashSet::new()),
+            processes: Mutex::new(HashMap::new()),
+            runs: Mutex::new(Runs::default()),
+            updates,
+            scheduler: config.scheduler.max_concurrent_runs.map(Semaphore::new),
             store: storage_handle,
         })
         .expect("");
+}
 
-    let targets_filter = warp::path!("api" / "targets").and(warp::get()).map(|| {
-        let state = STATE.get().unwrap();
-        let running = state.running.try_lock().unwrap();
+/// The Files of the Server that the Targets must not be able to read
+fn secret_paths(config: &Config, config_file: Option<&Path>) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = config_file.into_iter().map(PathBuf::from).collect();
+    if let Some(tls) = &config.tls {
+        paths.push(tls.key.clone());
+    }
+    if let StorageConfig::Sqlite { path } = &config.storage {
+        // SQLite also keeps Parts of the Database in Files next to it
+        for suffix in ["", "-wal", "-shm", "-journal"] {
+            let mut file = path.clone().into_os_string();
+            file.push(suffix);
+            paths.push(file.into());
+        }
+    }
+    if let Some(BlobConfig::Filesystem { path }) = &config.blobs {
+        paths.push(path.clone());
+    }
 
-        serde_json::to_string::<HashSet<String>>(&running).unwrap()
-    });
-    let results_filter = warp::path!("api" / "results")
+    paths
+}
+
+/// Creates the Bubblewrap Sandbox, which hides the Secrets of the Server
+fn create_sandbox(
+    config: &Config,
+    config_file: Option<&Path>,
+    bwrap: Option<&PathBuf>,
+) -> Bubblewrap {
+    let mut sandbox = Bubblewrap::default();
+    if let Some(bwrap) = bwrap {
+        sandbox.program = bwrap.clone();
+    }
+    sandbox.hidden_paths = secret_paths(config, config_file);
+
+    sandbox
+}
+
+/// Creates the Launcher for the Commands that are run outside of a Run, like cloning a Project to
+/// discover its Targets, so they are as restricted as the Runs themselves
+fn create_launcher(config: &Config, config_file: Option<&Path>) -> Arc<dyn Launcher + Send + Sync> {
+    match &config.runner {
+        RunnerConfig::Sandbox { bwrap, .. } => {
+            Arc::new(create_sandbox(config, config_file, bwrap.as_ref()))
+        }
+        _ => Arc::new(Direct),
+    }
+}
+
+/// Creates the configured Blob-Store
+fn create_blobs(config: &BlobConfig) -> Result<Arc<dyn BlobStore + Send + Sync>, String> {
+    match config {
+        BlobConfig::Filesystem { path } => Ok(Arc::new(FsBlobStore::new(path))),
+        BlobConfig::S3 {
+            endpoint,
+            bucket,
+            region,
+            prefix,
+            access_key,
+            secret_key,
+        } => {
+            let access_key = match access_key {
+                Some(k) => k.clone(),
+                None => std::env::var("AWS_ACCESS_KEY_ID")
+                    .map_err(|_| "Missing the S3 access_key or AWS_ACCESS_KEY_ID".to_string())?,
+            };
+            let secret_key = match secret_key {
+                Some(k) => k.clone(),
+                None => std::env::var("AWS_SECRET_ACCESS_KEY").map_err(|_| {
+                    "Missing the S3 secret_key or AWS_SECRET_ACCESS_KEY".to_string()
+                })?,
+            };
+
+            let store = S3BlobStore::new(
+                endpoint,
+                bucket.clone(),
+                region.clone(),
+                Credentials {
+                    access_key,
+                    secret_key,
+                },
+            )?
+            .with_prefix(prefix.clone());
+            Ok(Arc::new(store))
+        }
+    }
+}
+
+/// Creates the configured Runner, the remote Runner hands the Targets to the Coordinator
+fn create_runner(
+    config: &Config,
+    config_file: Option<&Path>,
+    coordinator: &Arc<Coordinator>,
+) -> Box<dyn runner::Runner + Send + Sync> {
+    let workspace = config.workspace.clone();
+
+    match &config.runner {
+        RunnerConfig::Process { cgroup } => {
+            let mut runner = ProcessRunner::new(workspace);
+            if let Some(cgroup) = cgroup {
+                runner = runner.with_cgroup(cgroup);
+            }
+            Box::new(runner)
+        }
+        RunnerConfig::Sandbox { cgroup, bwrap } => {
+            let sandbox = create_sandbox(config, config_file, bwrap.as_ref());
+            let mut runner = SandboxRunner::with_sandbox(workspace, sandbox);
+            if let Some(cgroup) = cgroup {
+                runner = runner.with_cgroup(cgroup);
+            }
+            Box::new(runner)
+        }
+        RunnerConfig::Container {
+            image,
+            socket,
+            memory_mb,
+            cpus,
+        } => {
+            let mut runner = ContainerRunner::new(workspace, image);
+            if let Some(socket) = socket {
+                runner = runner.with_socket(socket);
+            }
+            if let Some(memory) = memory_mb {
+                runner = runner.with_memory_limit(memory * 1024 * 1024);
+            }
+            if let Some(cpus) = cpus {
+                runner = runner.with_cpu_limit(*cpus);
+            }
+            Box::new(runner)
+        }
+        RunnerConfig::Remote => Box::new(RemoteRunner::new(coordinator.clone())),
+    }
+}
+
+async fn serve<R>(
+    config: Config,
+    runner: Arc<R>,
+    launcher: Arc<dyn Launcher + Send + Sync>,
+    coordinator: Arc<Coordinator>,
+) where
+    R: runner::Runner + Send + Sync + 'static,
+{
+    setup_state(&config).await;
+    if let Err(e) = auth::bootstrap(&config.workspace).await {
+        eprintln!("Bootstrapping the Admin: {}", e);
+        std::process::exit(1);
+    }
+
+    let targets_filter = warp::path!("api" / "targets")
         .and(warp::get())
-        .and(warp::query())
-        .then(|params: HashMap<String, String>| async move {
-            let pname = match params.get("pname") {
-                Some(n) => n,
-                None => return "Missing Name".to_string(),
+        .and(auth::identity())
+        .then(|identity: Identity| async move {
+            let visible = match auth::visible_projects(&identity).await {
+                Ok(v) => v,
+                Err(e) => return e.reply(),
             };
 
             let state = STATE.get().unwrap();
+            let running = state.running.lock().unwrap();
+            let running: Vec<&String> = running
+                .iter()
+                .filter(|(project, _)| {
+                    visible
+                        .as_ref()
+                        .map(|v| v.contains(project))
+                        .unwrap_or(true)
+                })
+                .map(|(_, name)| name)
+                .collect();
+
+            warp::reply::with_status(serde_json::to_string(&running).unwrap(), StatusCode::OK)
+        });
+    let results_filter = warp::path!("api" / "results")
+        .and(warp::get())
+        .and(auth::identity())
+        .and(warp::query())
+        .then(
+            |identity: Identity, params: HashMap<String, String>| async move {
+                let pname = match params.get("pname") {
+                    Some(n) => n,
+                    None => {
+                        return warp::reply::with_status(
+                            "Missing Name".to_string(),
+                            StatusCode::BAD_REQUEST,
+                        )
+                    }
+                };
+                match auth::authorized(&identity, pname, Role::Viewer).await {
+                    Ok(true) => {}
+                    Ok(false) => return forbidden(),
+                    Err(e) => return e.reply(),
+                }
+
+                let state = STATE.get().unwrap();
+
+                let results = match state.store.load_results(pname.to_string()).await {
+                    Ok(r) => r,
+                    Err(e) => return e.reply(),
+                };
+
+                let content = serde_json::to_string::<Vec<FuzzResult>>(results.as_ref()).unwrap();
 
-            let results = state.store.load_results(pname.to_string()).await;
+                warp::reply::with_status(content, StatusCode::OK)
+            },
+        );
+    let runs_filter = warp::path!("api" / "runs")
+        .and(warp::get())
+        .and(auth::identity())
+        .and(warp::query())
+        .then(
+            |identity: Identity, params: HashMap<String, String>| async move {
+                let visible = match auth::visible_projects(&identity).await {
+                    Ok(v) => v,
+                    Err(e) => return e.reply(),
+                };
 
-            let content = serde_json::to_string::<Vec<FuzzResult>>(results.as_ref()).unwrap();
+                let state = STATE.get().unwrap();
+                let runs: Vec<RunRecord> = state
+                    .runs
+                    .lock()
+                    .unwrap()
+                    .records()
+                    .into_iter()
+                    .filter(|r| params.get("pname").map(|p| &r.project == p).unwrap_or(true))
+                    .filter(|r| {
+                        visible
+                            .as_ref()
+                            .map(|v| v.contains(&r.project))
+                            .unwrap_or(true)
+                    })
+                    .collect();
 
-            content
+                warp::reply::with_status(serde_json::to_string(&runs).unwrap(), StatusCode::OK)
+            },
+        );
+    let updates_filter = warp::path!("api" / "updates")
+        .and(warp::get())
+        .and(auth::identity())
+        .then(|identity: Identity| async move {
+            // Projects the User becomes a Member of later on are only visible after reconnecting
+            let visible = match auth::visible_projects(&identity).await {
+                Ok(v) => v,
+                Err(e) => return e.reply().into_response(),
+            };
+
+            let state = STATE.get().unwrap();
+            let stream = state
+                .updates
+                .stream()
+                .filter(move |update| {
+                    let visible = visible
+                        .as_ref()
+                        .map(|v| v.iter().any(|p| p == update.project()))
+                        .unwrap_or(true);
+                    futures_util::future::ready(visible)
+                })
+                .map(|update| warp::sse::Event::default().json_data(update));
+
+            warp::sse::reply(warp::sse::keep_alive().stream(stream)).into_response()
         });
     let start_filter = warp::path!("api" / "run")
         .and(warp::post())
+        .and(auth::identity())
         .and(warp::body::json())
-        .then(move |content: RunRequest| {
+        .then(move |identity: Identity, content: RunRequest| {
             let runner = runner.clone();
             async move {
+                match auth::authorized(&identity, &content.pname, Role::Maintainer).await {
+                    Ok(true) => {}
+                    Ok(false) => return forbidden(),
+                    Err(e) => return e.reply(),
+                }
+
                 let state = STATE.get().unwrap();
-                let project = state.store.load_project(&content.pname).await.unwrap();
-                let target = project
-                    .targets
-                    .iter()
-                    .find(|t| t.name == content.name)
-                    .unwrap();
+                let project = match state.store.load_project(&content.pname).await {
+                    Ok(Some(p)) => p,
+                    Ok(None) => {
+                        return warp::reply::with_status(
+                            "Unknown Project".to_string(),
+                            StatusCode::NOT_FOUND,
+                        )
+                    }
+                    Err(e) => return e.reply(),
+                };
+                let target = match project.targets.iter().find(|t| t.name == content.name) {
+                    Some(t) => t,
+                    None => {
+                        return warp::reply::with_status(
+                            "Unknown Target".to_string(),
+                            StatusCode::NOT_FOUND,
+                        )
+                    }
+                };
 
                 tokio::spawn(run(content, runner, target.clone(), project.source));
 
-                ""
+                warp::reply::with_status(String::new(), StatusCode::OK)
             }
         });
 
     let update_project_filter = warp::path!("api" / "projects" / "update")
         .and(warp::post())
+        .and(auth::identity())
         .and(warp::body::json())
-        .then(|proj: Project| async move {
-            dbg!(&proj);
+        .then(|identity: Identity, proj: Project| async move {
+            // Changing the Source changes what gets built and run, so only Admins manage Projects
+            if !identity.admin {
+                return forbidden();
+            }
 
             let state = STATE.get().unwrap();
-            state.store.update_project(proj).await;
 
-            ""
+            if let Err(e) = state.store.update_project(proj).await {
+                return e.reply();
+            }
+
+            warp::reply::with_status(String::new(), StatusCode::OK)
         });
     let remove_project_filter = warp::path!("api" / "projects" / "remove")
         .and(warp::post())
+        .and(auth::identity())
         .and(warp::query())
-        .then(|query: HashMap<String, String>| async move {
-            let name = match query.get("pname") {
-                Some(n) => n,
-                None => return "Missing pname",
-            };
+        .then(
+            |identity: Identity, query: HashMap<String, String>| async move {
+                let name = match query.get("pname") {
+                    Some(n) => n,
+                    None => {
+                        return warp::reply::with_status(
+                            "Missing pname".to_string(),
+                            StatusCode::BAD_REQUEST,
+                        )
+                    }
+                };
+                if !identity.admin {
+                    return forbidden();
+                }
 
-            let state = STATE.get().unwrap();
-            state.store.remove_project(name.to_string()).await;
+                let state = STATE.get().unwrap();
+                if let Err(e) = state.store.remove_project(name.to_string()).await {
+                    return e.reply();
+                }
 
-            ""
-        });
+                warp::reply::with_status(String::new(), StatusCode::OK)
+            },
+        );
     let list_projects_filter = warp::path!("api" / "projects" / "list")
         .and(warp::get())
-        .then(|| async move {
+        .and(auth::identity())
+        .then(|identity: Identity| async move {
+            let visible = match auth::visible_projects(&identity).await {
+                Ok(v) => v,
+                Err(e) => return e.reply(),
+            };
+
             let state = STATE.get().unwrap();
-            let projects = state.store.load_projects().await;
+            let projects = match state.store.load_projects().await {
+                Ok(p) => p,
+                Err(e) => return e.reply(),
+            };
+            let projects: Vec<Project> = projects
+                .into_iter()
+                .filter(|p| {
+                    visible
+                        .as_ref()
+                        .map(|v| v.contains(&p.name))
+                        .unwrap_or(true)
+                })
+                .collect();
 
-            serde_json::to_string(&projects).unwrap()
+            warp::reply::with_status(serde_json::to_string(&projects).unwrap(), StatusCode::OK)
         });
     let add_project_target = warp::path!("api" / "projects" / "targets" / "add")
         .and(warp::post())
+        .and(auth::identity())
         .and(warp::query::<HashMap<String, String>>())
         .and(warp::body::json::<Target>())
-        .then(|query: HashMap<String, String>, target| async move {
-            let name = match query.get("pname") {
-                Some(n) => n,
-                None => {
-                    return "Missing pname";
+        .then(
+            |identity: Identity, query: HashMap<String, String>, target: Target| async move {
+                let name = match query.get("pname") {
+                    Some(n) => n,
+                    None => {
+                        return warp::reply::with_status(
+                            "Missing pname".to_string(),
+                            StatusCode::BAD_REQUEST,
+                        );
+                    }
+                };
+                match auth::authorized(&identity, name, Role::Maintainer).await {
+                    Ok(true) => {}
+                    Ok(false) => return forbidden(),
+                    Err(e) => return e.reply(),
                 }
-            };
 
-            let state = STATE.get().unwrap();
-            state
-                .store
-                .add_project_target(name.to_string(), target)
-                .await;
+                if let Err(e) = target.validate() {
+                    return warp::reply::with_status(e, StatusCode::BAD_REQUEST);
+                }
 
-            ""
-        });
+                let state = STATE.get().unwrap();
+                if let Err(e) = state
+                    .store
+                    .add_project_target(name.to_string(), target)
+                    .await
+                {
+                    return e.reply();
+                }
+
+                warp::reply::with_status(String::new(), StatusCode::OK)
+            },
+        );
+    let get_project_target = warp::path!("api" / "projects" / "targets")
+        .and(warp::get())
+        .and(auth::identity())
+        .and(warp::query::<HashMap<String, String>>())
+        .then(
+            |identity: Identity, query: HashMap<String, String>| async move {
+                let (project_name, target_name) = match (query.get("pname"), query.get("name")) {
+                    (Some(p), Some(n)) => (p, n),
+                    _ => {
+                        return warp::reply::with_status(
+                            "Missing pname or name".to_string(),
+                            StatusCode::BAD_REQUEST,
+                        )
+                    }
+                };
+                match auth::authorized(&identity, project_name, Role::Viewer).await {
+                    Ok(true) => {}
+                    Ok(false) => return forbidden(),
+                    Err(e) => return e.reply(),
+                }
+
+                let state = STATE.get().unwrap();
+                match state
+                    .store
+                    .load_target(project_name.to_string(), target_name.to_string())
+                    .await
+                {
+                    Ok(Some(target)) => warp::reply::with_status(
+                        serde_json::to_string(&target).unwrap(),
+                        StatusCode::OK,
+                    ),
+                    Ok(None) => warp::reply::with_status(
+                        "Unknown Target".to_string(),
+                        StatusCode::NOT_FOUND,
+                    ),
+                    Err(e) => e.reply(),
+                }
+            },
+        );
+    let update_project_target = warp::path!("api" / "projects" / "targets" / "update")
+        .and(warp::post())
+        .and(auth::identity())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(warp::body::json::<Target>())
+        .then(
+            |identity: Identity, query: HashMap<String, String>, target: Target| async move {
+                let name = match query.get("pname") {
+                    Some(n) => n,
+                    None => {
+                        return warp::reply::with_status(
+                            "Missing pname".to_string(),
+                            StatusCode::BAD_REQUEST,
+                        );
+                    }
+                };
+                match auth::authorized(&identity, name, Role::Maintainer).await {
+                    Ok(true) => {}
+                    Ok(false) => return forbidden(),
+                    Err(e) => return e.reply(),
+                }
+
+                if let Err(e) = target.validate() {
+                    return warp::reply::with_status(e, StatusCode::BAD_REQUEST);
+                }
+
+                // Unlike adding a Target, updating it never creates a new one
+                let state = STATE.get().unwrap();
+                match state
+                    .store
+                    .update_project_target(name.to_string(), target)
+                    .await
+                {
+                    Ok(true) => warp::reply::with_status(String::new(), StatusCode::OK),
+                    Ok(false) => warp::reply::with_status(
+                        "Unknown Target".to_string(),
+                        StatusCode::NOT_FOUND,
+                    ),
+                    Err(e) => e.reply(),
+                }
+            },
+        );
     let remove_project_target = warp::path!("api" / "projects" / "targets" / "remove")
         .and(warp::post())
+        .and(auth::identity())
         .and(warp::query::<HashMap<String, String>>())
-        .then(|query: HashMap<String, String>| async move {
-            let project_name = match query.get("pname") {
-                Some(n) => n,
-                None => return "Missing pname",
-            };
-            let target_name = match query.get("name") {
-                Some(n) => n,
-                None => return "Missing name",
-            };
+        .then(
+            |identity: Identity, query: HashMap<String, String>| async move {
+                let (project_name, target_name) = match (query.get("pname"), query.get("name")) {
+                    (Some(p), Some(n)) => (p, n),
+                    _ => {
+                        return warp::reply::with_status(
+                            "Missing pname or name".to_string(),
+                            StatusCode::BAD_REQUEST,
+                        )
+                    }
+                };
+                match auth::authorized(&identity, project_name, Role::Maintainer).await {
+                    Ok(true) => {}
+                    Ok(false) => return forbidden(),
+                    Err(e) => return e.reply(),
+                }
+
+                let state = STATE.get().unwrap();
+                if let Err(e) = state
+                    .store
+                    .remove_project_target(project_name.to_string(), target_name.to_string())
+                    .await
+                {
+                    return e.reply();
+                }
+
+                warp::reply::with_status(String::new(), StatusCode::OK)
+            },
+        );
+    let workspace = config.workspace.clone();
+    let discover_targets = warp::path!("api" / "projects" / "discover")
+        .and(warp::post())
+        .and(auth::identity())
+        .and(warp::query::<HashMap<String, String>>())
+        .then(move |identity: Identity, query: HashMap<String, String>| {
+            let workspace = workspace.clone();
+            let launcher = launcher.clone();
+            async move {
+                let name = match query.get("pname") {
+                    Some(n) => n,
+                    None => {
+                        return warp::reply::with_status(
+                            "Missing pname".to_string(),
+                            StatusCode::BAD_REQUEST,
+                        )
+                    }
+                };
+                match auth::authorized(&identity, name, Role::Maintainer).await {
+                    Ok(true) => {}
+                    Ok(false) => return forbidden(),
+                    Err(e) => return e.reply(),
+                }
+
+                let state = STATE.get().unwrap();
+                let project = match state.store.load_project(name).await {
+                    Ok(Some(p)) => p,
+                    Ok(None) => {
+                        return warp::reply::with_status(
+                            "Unknown Project".to_string(),
+                            StatusCode::NOT_FOUND,
+                        )
+                    }
+                    Err(e) => return e.reply(),
+                };
+
+                let targets = match discovery::discover_source(
+                    &workspace,
+                    name,
+                    &project.source,
+                    launcher.as_ref(),
+                )
+                .await
+                {
+                    Ok(t) => t,
+                    Err(e) => return warp::reply::with_status(e, StatusCode::BAD_GATEWAY),
+                };
+
+                if query.get("create").map(|c| c == "true").unwrap_or(false) {
+                    if let Err(e) = discovery::create_missing(&project, &targets).await {
+                        return e.reply();
+                    }
+                }
+
+                warp::reply::with_status(serde_json::to_string(&targets).unwrap(), StatusCode::OK)
+            }
+        });
+    let workspace = config.workspace.clone();
+    let export_filter = warp::path!("api" / "export")
+        .and(warp::get())
+        .and(auth::identity())
+        .and(warp::query::<HashMap<String, String>>())
+        .then(move |identity: Identity, query: HashMap<String, String>| {
+            let workspace = workspace.clone();
+            async move {
+                if !identity.admin {
+                    return archive_reply(Err(forbidden()));
+                }
+
+                let names: Vec<String> = query
+                    .get("projects")
+                    .map(|p| p.split(',').filter(|n| !n.is_empty()).map(String::from))
+                    .into_iter()
+                    .flatten()
+                    .collect();
+
+                let archive = archive::export(&workspace, &names)
+                    .await
+                    .map_err(|e| warp::reply::with_status(e, StatusCode::BAD_REQUEST));
+                archive_reply(archive)
+            }
+        });
+    let workspace = config.workspace.clone();
+    let import_filter = warp::path!("api" / "import")
+        .and(warp::post())
+        .and(auth::identity())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(warp::body::content_length_limit(MAX_ARCHIVE_SIZE))
+        .and(warp::body::bytes())
+        .then(
+            move |identity: Identity,
+                  query: HashMap<String, String>,
+                  body: warp::hyper::body::Bytes| {
+                let workspace = workspace.clone();
+                async move {
+                    if !identity.admin {
+                        return forbidden();
+                    }
+
+                    let conflict = match query.get("conflict").map(|c| c.as_str()) {
+                        None | Some("fail") => Conflict::Fail,
+                        Some("skip") => Conflict::Skip,
+                        Some("replace") => Conflict::Replace,
+                        Some(_) => {
+                            return warp::reply::with_status(
+                                "Invalid conflict".to_string(),
+                                StatusCode::BAD_REQUEST,
+                            )
+                        }
+                    };
+
+                    match archive::import(&workspace, &body, conflict).await {
+                        Ok(report) => warp::reply::with_status(
+                            serde_json::to_string(&report).unwrap(),
+                            StatusCode::OK,
+                        ),
+                        Err(e) => warp::reply::with_status(e, StatusCode::CONFLICT),
+                    }
+                }
+            },
+        );
+    let list_members = warp::path!("api" / "projects" / "members")
+        .and(warp::get())
+        .and(auth::identity())
+        .and(warp::query::<HashMap<String, String>>())
+        .then(
+            |identity: Identity, query: HashMap<String, String>| async move {
+                let name = match query.get("pname") {
+                    Some(n) => n,
+                    None => {
+                        return warp::reply::with_status(
+                            "Missing pname".to_string(),
+                            StatusCode::BAD_REQUEST,
+                        )
+                    }
+                };
+                match auth::authorized(&identity, name, Role::Viewer).await {
+                    Ok(true) => {}
+                    Ok(false) => return forbidden(),
+                    Err(e) => return e.reply(),
+                }
+
+                let state = STATE.get().unwrap();
+                let members = match state.store.load_members(name.to_string()).await {
+                    Ok(m) => m,
+                    Err(e) => return e.reply(),
+                };
+
+                warp::reply::with_status(serde_json::to_string(&members).unwrap(), StatusCode::OK)
+            },
+        );
+    let add_member = warp::path!("api" / "projects" / "members" / "add")
+        .and(warp::post())
+        .and(auth::identity())
+        .and(warp::body::json::<Member>())
+        .then(|identity: Identity, member: Member| async move {
+            if !identity.admin {
+                return forbidden();
+            }
 
             let state = STATE.get().unwrap();
-            state
-                .store
-                .remove_project_target(project_name.to_string(), target_name.to_string())
-                .await;
+            match state.store.load_user(member.user.clone()).await {
+                Ok(Some(_)) => {}
+                Ok(None) => {
+                    return warp::reply::with_status(
+                        "Unknown user".to_string(),
+                        StatusCode::BAD_REQUEST,
+                    )
+                }
+                Err(e) => return e.reply(),
+            }
+
+            if let Err(e) = state.store.store_member(member).await {
+                return e.reply();
+            }
 
-            ""
+            warp::reply::with_status(String::new(), StatusCode::OK)
         });
+    let remove_member = warp::path!("api" / "projects" / "members" / "remove")
+        .and(warp::post())
+        .and(auth::identity())
+        .and(warp::query::<HashMap<String, String>>())
+        .then(
+            |identity: Identity, query: HashMap<String, String>| async move {
+                let (project_name, user) = match (query.get("pname"), query.get("user")) {
+                    (Some(p), Some(u)) => (p, u),
+                    _ => {
+                        return warp::reply::with_status(
+                            "Missing pname or user".to_string(),
+                            StatusCode::BAD_REQUEST,
+                        )
+                    }
+                };
+                if !identity.admin {
+                    return forbidden();
+                }
+
+                let state = STATE.get().unwrap();
+                if let Err(e) = state
+                    .store
+                    .remove_member(project_name.to_string(), user.to_string())
+                    .await
+                {
+                    return e.reply();
+                }
+
+                warp::reply::with_status(String::new(), StatusCode::OK)
+            },
+        );
 
-    let content = warp::get().and(warp::fs::dir("./assets/"));
+    let content = warp::get().and(warp::fs::dir(config.assets.clone()));
 
     let server = targets_filter
+        .or(auth::routes())
         .or(results_filter)
+        .or(runs_filter)
+        .or(updates_filter)
         .or(start_filter)
         .or(update_project_filter)
         .or(remove_project_filter)
         .or(list_projects_filter)
+        .or(get_project_target)
         .or(add_project_target)
+        .or(update_project_target)
         .or(remove_project_target)
+        .or(discover_targets)
+        .or(list_members)
+        .or(add_member)
+        .or(remove_member)
+        .or(export_filter)
+        .or(import_filter)
+        .or(remote::routes(coordinator))
         .or(content)
+        .recover(auth::handle_rejection)
         .with(
             warp::cors()
-                .allow_origins(["http://192.168.178.22:5000", "http://192.168.178.22:5000/"])
+                .allow_origins(config.allowed_origins.iter().map(|o| o.as_str()))
                 .allow_methods(["GET", "POST", "FETCH"])
                 .allow_credentials(true)
-                .allow_headers(["content-type", "content-length"])
+                .allow_headers(["content-type", "content-length", "authorization"])
                 .build(),
         );
-    warp::serve(server).run(([0, 0, 0, 0], 8080)).await;
+    match &config.tls {
+        Some(tls) => {
+            warp::serve(server)
+                .tls()
+                .cert_path(&tls.cert)
+                .key_path(&tls.key)
+                .run(config.bind)
+                .await
+        }
+        None => warp::serve(server).run(config.bind).await,
+    };
+}
+
+/// Sends the Archive as a Download or the Error as is
+fn archive_reply(
+    archive: Result<Vec<u8>, warp::reply::WithStatus<String>>,
+) -> warp::reply::Response {
+    use warp::Reply;
+
+    match archive {
+        Ok(archive) => warp::reply::with_header(
+            archive,
+            "content-disposition",
+            "attachment; filename=\"cfuzz-export.tar\"",
+        )
+        .into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+fn forbidden() -> warp::reply::WithStatus<String> {
+    warp::reply::with_status("Forbidden".to_string(), StatusCode::FORBIDDEN)
 }
@@ -0,0 +1,116 @@
+//! The `cfuzz.toml` in the Repository of a Project
+//!
+//! A Project can describe its Targets in a `cfuzz.toml` at the Root of its Repository, so that the
+//! Fuzzing Configuration lives next to the Harnesses. Whenever the Project is checked out for a
+//! Run, the Targets of the Project are replaced with the ones from the File.
+//!
+//! ```toml
+//! [[targets]]
+//! name = "parse"
+//! folder = "."
+//! repeating = true
+//! sanitizers = ["address"]
+//! budget_secs = 3600
+//! interval_secs = 600
+//!
+//! [targets.target.CargoFuzz]
+//! name = "parse"
+//! release = true
+//!
+//! [targets.limits]
+//! memory_mb = 2048
+//! ```
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{project::Target, storage::StorageError, STATE};
+
+/// The Name of the File in the Root of the Repository
+pub const FILE: &str = "cfuzz.toml";
+
+/// The Contents of a `cfuzz.toml`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(default)]
+    pub targets: Vec<Target>,
+}
+
+impl Manifest {
+    /// Loads the Manifest from the Checkout, if the Repository contains one
+    pub fn load(repo: &Path) -> Result<Option<Self>, String> {
+        let path = repo.join(FILE);
+        let content = match std::fs::read_to_string(&path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Reading {}: {}", FILE, e)),
+        };
+
+        let manifest: Self =
+            toml::from_str(&content).map_err(|e| format!("Parsing {}: {}", FILE, e))?;
+        manifest.validate()?;
+
+        Ok(Some(manifest))
+    }
+
+    /// Checks that all the Targets are valid and have unique Names
+    pub fn validate(&self) -> Result<(), String> {
+        for (i, target) in self.targets.iter().enumerate() {
+            target
+                .validate()
+                .map_err(|e| format!("Target {:?}: {}", target.name, e))?;
+
+            if self.targets[..i].iter().any(|t| t.name == target.name) {
+                return Err(format!("Duplicate Target {:?}", target.name));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the stored Targets of the Project with the ones of the Manifest, only changing
+    /// the Targets that are actually different
+    pub async fn sync(&self, project: &str) -> Result<(), StorageError> {
+        let store = match STATE.get() {
+            Some(state) => &state.store,
+            // Agents have no Storage, so only the Run itself uses the Manifest
+            None => return Ok(()),
+        };
+        let stored = match store.load_project(project).await? {
+            Some(p) => p.targets,
+            None => return Ok(()),
+        };
+
+        for target in stored.iter() {
+            if !self.targets.iter().any(|t| t.name == target.name) {
+                store
+                    .remove_project_target(project.to_string(), target.name.clone())
+                    .await?;
+            }
+        }
+        for target in self.targets.iter() {
+            if !stored.contains(target) {
+                store
+                    .add_project_target(project.to_string(), target.clone())
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The current Definition of the Target, as a single Entry of its Sanitizer-Matrix
+    pub fn target(&self, current: &Target) -> Option<Target> {
+        let target = self.targets.iter().find(|t| t.name == current.name)?;
+
+        // The Target of a Run only contains the Sanitizer of that Run
+        let sanitizer = current.sanitizers.first().copied();
+        target
+            .matrix()
+            .into_iter()
+            .find(|(s, _)| *s == sanitizer)
+            .map(|(_, t)| t)
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
 /// A single Project which could contain multiple Targets
@@ -12,7 +14,7 @@ pub struct Project {
 }
 
 /// A single Source for a Project
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub enum Source {
     /// A Git Repository as Source for the Project
     Git {
@@ -22,7 +24,7 @@ pub enum Source {
 }
 
 /// A single Fuzzing Target for a Project
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Target {
     /// The Name of the Target
     pub name: String,
@@ -31,15 +33,261 @@ pub struct Target {
     /// The actual Target to run
     pub target: RunTarget,
     /// If the Target should be executed in a loop or only once
+    #[serde(default)]
     pub repeating: bool,
+    /// The Sanitizers the Target should be run with, where every Sanitizer results in its own Run
+    #[serde(default)]
+    pub sanitizers: Vec<Sanitizer>,
+    /// The Number of Fuzzer Processes that should work on the Target in parallel
+    #[serde(default = "default_workers")]
+    pub workers: usize,
+    /// The Resource-Limits for every Run of the Target
+    #[serde(default)]
+    pub limits: Limits,
+    /// The maximum Duration of a single Run in Seconds, after which the Fuzzer is stopped
+    #[serde(default)]
+    pub budget_secs: Option<u64>,
+    /// The Seconds to wait between two Runs of a repeating Target
+    #[serde(default)]
+    pub interval_secs: Option<u64>,
+}
+
+/// The Resources a single Run is allowed to use
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Limits {
+    /// The Memory in MB every Fuzzer Process may use
+    pub memory_mb: Option<u64>,
+    /// The Number of CPUs all the Processes of the Run may use together
+    pub cpus: Option<f64>,
+    /// The Disk-Space in MB the Workspace and Corpus of the Run may use
+    pub disk_mb: Option<u64>,
+}
+
+fn default_workers() -> usize {
+    1
 }
 
 /// A single runnable Fuzzing Target that specifies how the Target should be fuzzed
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub enum RunTarget {
     /// The Cargo-Fuzz
     CargoFuzz {
         /// The Name of the fuzzing Target
         name: String,
+        /// The additional Options passed to cargo-fuzz and libFuzzer
+        #[serde(flatten)]
+        options: CargoFuzzOptions,
+    },
+    /// The Honggfuzz-rs Fuzzer, run using `cargo hfuzz`
+    Honggfuzz {
+        /// The Name of the fuzzing Target
+        name: String,
+    },
+    /// An arbitrary Fuzzer that is run using the given Command
+    Command {
+        /// A Shell-Command that should be executed to build the Fuzzer before running it
+        #[serde(default)]
+        build: Option<String>,
+        /// The Program and its Arguments used to run the Fuzzer, the Path of the shared Corpus is
+        /// passed in the `CFUZZ_CORPUS` Environment Variable
+        run: Vec<String>,
+        /// The Folder, relative to the Target Folder, in which the Fuzzer stores its Crashes
+        artifacts_dir: String,
+        /// The Folder, relative to the Target Folder, in which the Fuzzer stores its Corpus
+        #[serde(default)]
+        corpus_dir: Option<String>,
+        /// Additional Environment Variables for building and running the Fuzzer
+        #[serde(default)]
+        env: HashMap<String, String>,
     },
 }
+
+/// The Options for running a Cargo-Fuzz Target
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CargoFuzzOptions {
+    /// The Sanitizer to build the Target with, uses the cargo-fuzz default if not set
+    pub sanitizer: Option<Sanitizer>,
+    /// Build the Target in release mode
+    pub release: bool,
+    /// Build the Target with debug assertions enabled
+    pub debug_assertions: bool,
+    /// The Toolchain to use, like `nightly`
+    pub toolchain: Option<String>,
+    /// The Number of concurrent Jobs libFuzzer should run
+    pub jobs: Option<usize>,
+    /// The maximum Length of the generated Inputs
+    pub max_len: Option<usize>,
+    /// The Path to a Dictionary, relative to the Target Folder
+    pub dict: Option<String>,
+    /// Only generate ASCII Inputs
+    pub only_ascii: bool,
+    /// Additional Arguments that are passed to libFuzzer
+    pub args: Vec<String>,
+}
+
+/// The Sanitizers supported by cargo-fuzz
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sanitizer {
+    Address,
+    Memory,
+    Thread,
+    None,
+}
+
+impl Sanitizer {
+    /// The Name of the Sanitizer as used by cargo-fuzz
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Address => "address",
+            Self::Memory => "memory",
+            Self::Thread => "thread",
+            Self::None => "none",
+        }
+    }
+}
+
+impl Target {
+    /// Checks if the Target is valid and could be run
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Missing Target name".to_string());
+        }
+        check_relative_path("folder", &self.folder)?;
+
+        if self.workers == 0 {
+            return Err("workers must be at least 1".to_string());
+        }
+        if self.limits.memory_mb == Some(0) || self.limits.disk_mb == Some(0) {
+            return Err("limits must be at least 1".to_string());
+        }
+        if let Some(cpus) = self.limits.cpus {
+            if !cpus.is_finite() || cpus <= 0.0 {
+                return Err(format!("Invalid cpus limit: {}", cpus));
+            }
+        }
+        if self.budget_secs == Some(0) {
+            return Err("budget_secs must be at least 1".to_string());
+        }
+        if self.interval_secs.is_some() && !self.repeating {
+            return Err("interval_secs is only supported for repeating Targets".to_string());
+        }
+
+        if !self.sanitizers.is_empty() && !matches!(self.target, RunTarget::CargoFuzz { .. }) {
+            return Err("sanitizers are only supported for CargoFuzz Targets".to_string());
+        }
+        for (i, sanitizer) in self.sanitizers.iter().enumerate() {
+            if self.sanitizers[..i].contains(sanitizer) {
+                return Err(format!("Duplicate sanitizer: {}", sanitizer.as_str()));
+            }
+        }
+
+        self.target.validate()
+    }
+
+    /// Expands the Target into the individual Runs for all of its Sanitizers.
+    ///
+    /// If there are no Sanitizers configured, this only contains the Target itself
+    pub fn matrix(&self) -> Vec<(Option<Sanitizer>, Target)> {
+        if self.sanitizers.is_empty() {
+            return vec![(None, self.clone())];
+        }
+
+        self.sanitizers
+            .iter()
+            .map(|sanitizer| {
+                let mut target = self.clone();
+                target.sanitizers = vec![*sanitizer];
+                if let RunTarget::CargoFuzz { options, .. } = &mut target.target {
+                    options.sanitizer = Some(*sanitizer);
+                }
+
+                (Some(*sanitizer), target)
+            })
+            .collect()
+    }
+}
+
+impl RunTarget {
+    /// Checks if the Configuration is valid
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::CargoFuzz { name, options } => {
+                check_name(name)?;
+                options.validate()
+            }
+            Self::Honggfuzz { name } => check_name(name),
+            Self::Command {
+                run,
+                artifacts_dir,
+                corpus_dir,
+                ..
+            } => {
+                if run.is_empty() {
+                    return Err("Missing Command to run".to_string());
+                }
+                check_relative_path("artifacts_dir", artifacts_dir)?;
+                if let Some(corpus_dir) = corpus_dir {
+                    check_relative_path("corpus_dir", corpus_dir)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl CargoFuzzOptions {
+    /// Checks if the Options are valid
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(toolchain) = &self.toolchain {
+            let valid = !toolchain.is_empty()
+                && toolchain
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_'));
+            if !valid {
+                return Err(format!("Invalid toolchain: {:?}", toolchain));
+            }
+        }
+        if self.jobs == Some(0) {
+            return Err("jobs must be at least 1".to_string());
+        }
+        if self.max_len == Some(0) {
+            return Err("max_len must be at least 1".to_string());
+        }
+        if let Some(dict) = &self.dict {
+            check_relative_path("dict", dict)?;
+        }
+        if let Some(arg) = self.args.iter().find(|a| !a.starts_with('-')) {
+            return Err(format!("Invalid libFuzzer argument: {:?}", arg));
+        }
+
+        Ok(())
+    }
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.starts_with('-') || name.contains(char::is_whitespace) {
+        return Err(format!("Invalid fuzzing Target name: {:?}", name));
+    }
+
+    Ok(())
+}
+
+/// Makes sure that the Path stays inside of the Project
+fn check_relative_path(field: &str, path: &str) -> Result<(), String> {
+    let path = std::path::Path::new(path);
+    let escapes = path.components().any(|c| {
+        !matches!(
+            c,
+            std::path::Component::Normal(_) | std::path::Component::CurDir
+        )
+    });
+    if escapes {
+        return Err(format!("{} has to be a relative Path inside the Project", field));
+    }
+
+    Ok(())
+}
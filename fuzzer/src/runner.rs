@@ -1,44 +1,132 @@
 use std::sync::Arc;
 
-use tokio::sync::oneshot;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
 
 use crate::FuzzTarget;
 
+pub mod container;
+mod libfuzzer;
+mod limits;
 pub mod process;
+pub mod remote;
+pub mod sandbox;
 
 /// A Runner is responsible for actually running Fuzzing Targets, this allows different deployments to
 /// use different ways of running their Targets.
+#[async_trait]
 pub trait Runner {
     /// Runs the given Target
     ///
-    /// The Fuzzing should be canceled when there is a message sent over the cancel-oneshot
-    fn run(&self, target: FuzzTarget, cancel: oneshot::Receiver<()>) -> Option<Vec<Vec<u8>>>;
+    /// The Progress of the Run should be reported using the Events-Sender and the Fuzzing should
+    /// be stopped when there is a message sent over the cancel-oneshot
+    async fn run(
+        &self,
+        target: FuzzTarget,
+        events: mpsc::UnboundedSender<Event>,
+        cancel: oneshot::Receiver<Stop>,
+    ) -> Option<RunOutput>;
+}
+
+/// Allows for selecting the Runner at Runtime
+#[async_trait]
+impl Runner for Box<dyn Runner + Send + Sync> {
+    async fn run(
+        &self,
+        target: FuzzTarget,
+        events: mpsc::UnboundedSender<Event>,
+        cancel: oneshot::Receiver<Stop>,
+    ) -> Option<RunOutput> {
+        self.as_ref().run(target, events, cancel).await
+    }
+}
+
+/// The Progress of a Run as reported by the Runner
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Event {
+    /// The Source of the Project is being checked out
+    Cloning,
+    /// The Fuzzer is being built
+    Building,
+    /// The Fuzzer was started with the given Number of Workers
+    Fuzzing { workers: usize },
+    /// The latest Statistics of a single Worker
+    Stats(Stats),
+    /// The Fuzzer found a new Crash
+    Artifact { name: String },
+    /// The Run completed
+    Finished {
+        crashes: usize,
+        exceeded: Option<Limit>,
+    },
+    /// The Run failed or was canceled
+    Failed,
+}
+
+/// The Statistics reported by a single Worker of the Fuzzer
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    pub worker: usize,
+    /// The Number of Executions so far
+    pub execs: u64,
+    pub execs_per_sec: u64,
+    /// The Number of covered Edges
+    pub coverage: u64,
+    /// The Number of Inputs in the Corpus
+    pub corpus: u64,
+    /// The Memory used by the Worker in MB
+    pub rss_mb: u64,
+}
+
+/// The Output of a Run that completed
+#[derive(Debug, Default, PartialEq)]
+pub struct RunOutput {
+    /// The Inputs that caused the Target to crash
+    pub artifacts: Vec<Vec<u8>>,
+    /// The Resource-Limit of the Target that was exceeded during the Run
+    pub exceeded: Option<Limit>,
+}
+
+/// The Resource-Limits that can be exceeded by a Run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Limit {
+    Memory,
+    Disk,
+}
+
+/// The Reason for stopping a Run before the Fuzzer exited on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Stop {
+    /// The Budget of the Run is used up, so it finishes with everything found so far
+    Budget,
+    /// The Run was canceled, by a User or because cfuzz is shutting down
+    Cancel,
+}
+
+/// Resolves once a cancel signal was received, simply dropping the Sender does not cancel the Run
+pub async fn canceled(cancel: oneshot::Receiver<Stop>) -> Stop {
+    match cancel.await {
+        Ok(stop) => stop,
+        Err(_) => std::future::pending().await,
+    }
 }
 
 /// A simple wrapper that allows you to run the given FuzzTarget with the provided Runner
 /// and waits until the Runner has finished
-pub async fn run_completion<R>(runner: Arc<R>, target: FuzzTarget) -> Option<Vec<Vec<u8>>>
+pub async fn run_completion<R>(
+    runner: Arc<R>,
+    target: FuzzTarget,
+    events: mpsc::UnboundedSender<Event>,
+) -> Option<RunOutput>
 where
     R: Runner + Send + Sync + 'static,
 {
-    let (sender, recv) = oneshot::channel();
-
-    let (res_sender, res_recv) = oneshot::channel();
+    let (_sender, recv) = oneshot::channel();
 
-    std::thread::spawn(move || {
-        match runner.run(target, recv) {
-            Some(r) => {
-                res_sender.send(r).unwrap();
-            }
-            None => {
-                println!("Error running Target")
-            }
-        };
-    });
-
-    let res = res_recv.await.ok();
-
-    drop(sender);
+    let res = runner.run(target, events.clone(), recv).await;
+    finished(&events, res.as_ref());
 
     res
 }
@@ -48,32 +136,42 @@ where
 pub async fn run_timeout<R>(
     runner: Arc<R>,
     target: FuzzTarget,
+    events: mpsc::UnboundedSender<Event>,
     timeout: std::time::Duration,
-) -> Option<Vec<Vec<u8>>>
+) -> Option<RunOutput>
 where
     R: Runner + Send + Sync + 'static,
 {
     let (sender, recv) = oneshot::channel();
 
-    let (res_sender, res_recv) = oneshot::channel();
-
-    std::thread::spawn(move || {
-        match runner.run(target, recv) {
-            Some(r) => {
-                res_sender.send(r).unwrap();
-            }
-            None => {
-                println!("Error running Target")
-            }
-        };
-    });
+    let run = runner.run(target, events.clone(), recv);
+    tokio::pin!(run);
 
-    // Spawn a future that should send a cancel signal after the given Timeout
-    tokio::spawn(async move {
-        tokio::time::sleep(timeout).await;
+    let res = tokio::select! {
+        res = &mut run => res,
+        _ = tokio::time::sleep(timeout) => {
+            // The Runner still needs to stop the Fuzzer and collect its Results
+            let _ = sender.send(Stop::Budget);
+            run.await
+        }
+    };
+    finished(&events, res.as_ref());
 
-        let _ = sender.send(());
-    });
+    res
+}
 
-    res_recv.await.ok()
+/// Reports the Outcome of the Run
+fn finished(events: &mpsc::UnboundedSender<Event>, output: Option<&RunOutput>) {
+    let event = match output {
+        Some(output) => Event::Finished {
+            crashes: output.artifacts.len(),
+            exceeded: output.exceeded,
+        },
+        None => {
+            println!("Error running Target");
+            Event::Failed
+        }
+    };
+
+    let _ = events.send(event);
 }
@@ -0,0 +1,471 @@
+//! A Runner that executes the Targets inside of Containers
+//!
+//! The Containers are managed using the Docker Engine API, which is also provided by Podman,
+//! over the local unix socket. The Source is checked out on the Host and then mounted into the
+//! Containers together with the shared Corpus. The Target is first built in its own Container
+//! and then every Worker is started in a separate Container without any Network access.
+//!
+//! All the Containers share the same Cargo Home, so the Dependencies downloaded while building
+//! are also available to the Workers, which run Cargo offline.
+
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use async_trait::async_trait;
+use serde_json::json;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::UnixStream,
+    sync::{mpsc, oneshot},
+};
+
+use crate::FuzzTarget;
+
+use super::{
+    canceled,
+    limits::{self, DiskWatch},
+    process::{
+        build_command, fuzz_command, prepare_folders, process_count, setup, shared_corpus,
+        target_artifacts, Cancel, Direct,
+    },
+    Event, Limit, RunOutput, Runner, Stop,
+};
+
+/// The Path at which the checkout is mounted inside of the Containers
+const WORKSPACE: &str = "/workspace";
+/// The Path at which the shared Corpus is mounted inside of the Containers
+const CORPUS: &str = "/corpus";
+/// The Path at which the shared Cargo Home is mounted inside of the Containers
+const CARGO_HOME: &str = "/cargo";
+
+/// A Runner that runs every Target inside of Containers
+pub struct ContainerRunner {
+    subfolder: PathBuf,
+    image: String,
+    docker: Docker,
+    memory: Option<u64>,
+    cpus: Option<f64>,
+}
+
+impl ContainerRunner {
+    /// Creates a new ContainerRunner that uses the given Image for all Targets and the default
+    /// Docker Socket
+    pub fn new<P, I>(path: P, image: I) -> Self
+    where
+        P: Into<PathBuf>,
+        I: Into<String>,
+    {
+        Self {
+            subfolder: path.into(),
+            image: image.into(),
+            docker: Docker {
+                socket: PathBuf::from("/var/run/docker.sock"),
+            },
+            memory: None,
+            cpus: None,
+        }
+    }
+
+    /// Use the given Socket to talk to Docker/Podman
+    pub fn with_socket<P>(mut self, socket: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.docker.socket = socket.into();
+        self
+    }
+
+    /// Limit the Memory of every Container to the given Number of Bytes, unless the Target has its
+    /// own Limit
+    pub fn with_memory_limit(mut self, bytes: u64) -> Self {
+        self.memory = Some(bytes);
+        self
+    }
+
+    /// Limit every Container to the given Number of CPUs, unless the Target has its own Limit
+    pub fn with_cpu_limit(mut self, cpus: f64) -> Self {
+        self.cpus = Some(cpus);
+        self
+    }
+
+    async fn run(
+        &self,
+        target: &FuzzTarget,
+        repo_dir: &Path,
+        corpus_dir: &Path,
+        events: &mpsc::UnboundedSender<Event>,
+        cancel: &mut Cancel,
+    ) -> Option<RunOutput> {
+        let repo_dir = std::fs::canonicalize(repo_dir).ok()?;
+        let project_path = repo_dir.join(&target.runner().folder);
+        let workdir = Path::new(WORKSPACE).join(&target.runner().folder);
+        let config = &target.runner().target;
+        let limits = &target.runner().limits;
+        let processes = process_count(config, target.runner().workers);
+
+        // The CPU-Limit of the Target is for the entire Run, so it is split between the Workers
+        let memory = limits.memory_mb.map(|m| m * 1024 * 1024).or(self.memory);
+        let cpus = limits.cpus.map(|c| c / processes as f64).or(self.cpus);
+
+        prepare_folders(&project_path, config).ok()?;
+        let cargo_home = self.cargo_home()?;
+
+        let binds = [
+            format!("{}:{}", repo_dir.display(), WORKSPACE),
+            format!("{}:{}", corpus_dir.display(), CORPUS),
+            format!("{}:{}", cargo_home.display(), CARGO_HOME),
+        ];
+
+        if let Some(cmd) = build_command(&project_path, config) {
+            let _ = events.send(Event::Building);
+            let id = self
+                .create(&cmd, &workdir, &binds, true, memory, cpus)
+                .await?;
+            let status = self
+                .wait_containers(&[id], cancel, std::future::pending())
+                .await;
+
+            match status {
+                Ok(Ok(0)) => {}
+                Ok(Ok(code)) => {
+                    println!("Building Target failed: {}", code);
+                    return None;
+                }
+                Ok(Err(limit)) => {
+                    println!("Building Target exceeded the {:?} Limit", limit);
+                    return None;
+                }
+                Err(_) => return None,
+            };
+        }
+
+        let mut ids = Vec::new();
+        for _ in 0..processes {
+            let cmd = fuzz_command(&project_path, target.runner(), Path::new(CORPUS))?;
+            match self
+                .create(&cmd, &workdir, &binds, false, memory, cpus)
+                .await
+            {
+                Some(id) => ids.push(id),
+                None => {
+                    self.remove_containers(&ids).await;
+                    return None;
+                }
+            };
+        }
+
+        let _ = events.send(Event::Fuzzing { workers: processes });
+
+        let writable = [repo_dir.as_path(), corpus_dir];
+        let disk = DiskWatch::new(&writable, limits);
+        // Running out of Budget still completes the Run with everything found so far
+        let exceeded = match self.wait_containers(&ids, cancel, disk.exceeded()).await {
+            Err(Stop::Cancel) => return None,
+            Ok(Err(limit)) => Some(limit),
+            Ok(Ok(_)) | Err(Stop::Budget)
+                if limits::fuzzer_out_of_memory(&project_path, config) =>
+            {
+                Some(Limit::Memory)
+            }
+            Ok(Ok(_)) | Err(Stop::Budget) => None,
+        };
+
+        Some(RunOutput {
+            artifacts: target_artifacts(&project_path, config)?,
+            exceeded,
+        })
+    }
+
+    /// The Cargo Home on the Host that is shared by all the Containers
+    fn cargo_home(&self) -> Option<PathBuf> {
+        let path = self.subfolder.join(".cargo");
+        if let Err(e) = std::fs::create_dir_all(&path) {
+            println!("Creating Cargo Home: {}", e);
+            return None;
+        }
+
+        std::fs::canonicalize(path).ok()
+    }
+
+    /// Creates and starts a new Container for the given Command
+    async fn create(
+        &self,
+        cmd: &Command,
+        workdir: &Path,
+        binds: &[String],
+        network: bool,
+        memory: Option<u64>,
+        cpus: Option<f64>,
+    ) -> Option<String> {
+        let mut args = vec![cmd.get_program().to_string_lossy().to_string()];
+        args.extend(cmd.get_args().map(|a| a.to_string_lossy().to_string()));
+
+        let mut env: Vec<_> = cmd
+            .get_envs()
+            .filter_map(|(k, v)| Some(format!("{}={}", k.to_str()?, v?.to_str()?)))
+            .collect();
+        env.push(format!("CARGO_HOME={}", CARGO_HOME));
+        if !network {
+            // Everything was already downloaded into the Cargo Home while building
+            env.push("CARGO_NET_OFFLINE=true".to_string());
+        }
+
+        let mut host_config = json!({
+            "Binds": binds,
+            "NetworkMode": if network { "default" } else { "none" },
+        });
+        if let Some(memory) = memory {
+            host_config["Memory"] = json!(memory);
+            host_config["MemorySwap"] = json!(memory);
+        }
+        if let Some(cpus) = cpus {
+            host_config["NanoCpus"] = json!((cpus * 1_000_000_000.0) as u64);
+        }
+
+        // Running as the User of the Host keeps all the Files in the bind-mounted Folders owned
+        // by it, so it can clean them up again
+        // SAFETY: getuid and getgid can not fail and have no memory safety requirements
+        let user = unsafe { format!("{}:{}", libc::getuid(), libc::getgid()) };
+
+        let body = json!({
+            "Image": self.image,
+            "Cmd": args,
+            "Env": env,
+            "User": user,
+            "WorkingDir": workdir,
+            "HostConfig": host_config,
+        });
+
+        let (mut status, mut response) = self
+            .docker
+            .request("POST", "/containers/create", Some(&body))
+            .await
+            .ok()?;
+        if status == 404 {
+            // The Image does not exist locally yet
+            self.docker
+                .request(
+                    "POST",
+                    &format!("/images/create?fromImage={}", encode(&self.image)),
+                    None,
+                )
+                .await
+                .ok()?;
+
+            (status, response) = self
+                .docker
+                .request("POST", "/containers/create", Some(&body))
+                .await
+                .ok()?;
+        }
+        if status != 201 {
+            println!(
+                "Creating Container: {} {}",
+                status,
+                String::from_utf8_lossy(&response)
+            );
+            return None;
+        }
+
+        let created: serde_json::Value = serde_json::from_slice(&response).ok()?;
+        let id = created["Id"].as_str()?.to_string();
+
+        let (status, _) = self
+            .docker
+            .request("POST", &format!("/containers/{}/start", id), None)
+            .await
+            .ok()?;
+        if status != 204 && status != 304 {
+            println!("Starting Container: {}", status);
+            self.remove_containers(&[id]).await;
+            return None;
+        }
+
+        Some(id)
+    }
+
+    /// Waits for the first Container to exit, until a cancel signal was received or until the
+    /// Watch reports an exceeded Limit and then stops and removes all the Containers.
+    ///
+    /// Returns the Exit-Code of the first Container, the exceeded Limit or the Reason the Run was
+    /// stopped
+    async fn wait_containers<W>(
+        &self,
+        ids: &[String],
+        cancel: &mut Cancel,
+        watch: W,
+    ) -> Result<Result<i64, Limit>, Stop>
+    where
+        W: Future<Output = Limit>,
+    {
+        tokio::pin!(watch);
+
+        let result = loop {
+            if let Some(exit) = self.exited(ids).await {
+                break Ok(exit);
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(std::time::Duration::from_secs(1)) => {}
+                stop = &mut *cancel => break Err(stop),
+                limit = &mut watch => break Ok(Err(limit)),
+            };
+        };
+
+        self.remove_containers(ids).await;
+
+        result
+    }
+
+    /// Checks if any of the Containers exited already
+    async fn exited(&self, ids: &[String]) -> Option<Result<i64, Limit>> {
+        for id in ids {
+            let response = self
+                .docker
+                .request("GET", &format!("/containers/{}/json", id), None)
+                .await;
+            let info: serde_json::Value = match response {
+                Ok((_, body)) => match serde_json::from_slice(&body) {
+                    Ok(i) => i,
+                    Err(_) => continue,
+                },
+                Err(_) => continue,
+            };
+
+            let state = &info["State"];
+            if state["Running"].as_bool().unwrap_or(true) {
+                continue;
+            }
+
+            if state["OOMKilled"].as_bool().unwrap_or(false) {
+                return Some(Err(Limit::Memory));
+            }
+            return Some(Ok(state["ExitCode"].as_i64().unwrap_or(-1)));
+        }
+
+        None
+    }
+
+    /// Stops and removes all the given Containers
+    async fn remove_containers(&self, ids: &[String]) {
+        for id in ids {
+            let _ = self
+                .docker
+                .request("POST", &format!("/containers/{}/stop?t=5", id), None)
+                .await;
+            let _ = self
+                .docker
+                .request("DELETE", &format!("/containers/{}?force=true", id), None)
+                .await;
+        }
+    }
+}
+
+#[async_trait]
+impl Runner for ContainerRunner {
+    async fn run(
+        &self,
+        mut target: FuzzTarget,
+        events: mpsc::UnboundedSender<Event>,
+        cancel: oneshot::Receiver<Stop>,
+    ) -> Option<RunOutput> {
+        let _ = events.send(Event::Cloning);
+        let (repo_dir, cleanup) = setup(&self.subfolder, &Direct, &mut target).await?;
+
+        let corpus_dir = shared_corpus(&self.subfolder, &target);
+
+        let mut cancel: Cancel = Box::pin(canceled(cancel));
+        let result = self
+            .run(&target, &repo_dir, &corpus_dir, &events, &mut cancel)
+            .await;
+
+        let _ = tokio::task::spawn_blocking(cleanup).await;
+
+        result
+    }
+}
+
+/// A minimal Client for the Docker Engine API
+struct Docker {
+    socket: PathBuf,
+}
+
+impl Docker {
+    /// Sends a single Request to the API and returns the Status-Code and Body of the Response
+    async fn request(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&serde_json::Value>,
+    ) -> std::io::Result<(u16, Vec<u8>)> {
+        let mut stream = UnixStream::connect(&self.socket).await?;
+
+        let body = body.map(|b| b.to_string()).unwrap_or_default();
+        let request = format!(
+            "{} /v1.41{} HTTP/1.1\r\nHost: docker\r\nConnection: close\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        );
+        stream.write_all(request.as_bytes()).await?;
+
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw).await?;
+
+        parse_response(&raw).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "Malformed HTTP Response")
+        })
+    }
+}
+
+/// Parses a HTTP/1.1 Response that was read until the Connection was closed
+fn parse_response(raw: &[u8]) -> Option<(u16, Vec<u8>)> {
+    let header_end = raw.windows(4).position(|w| w == b"\r\n\r\n")?;
+    let head = std::str::from_utf8(&raw[..header_end]).ok()?;
+    let body = &raw[header_end + 4..];
+
+    let mut lines = head.split("\r\n");
+    let status = lines.next()?.split(' ').nth(1)?.parse().ok()?;
+
+    let chunked = lines.any(|line| {
+        let line = line.to_ascii_lowercase();
+        line.starts_with("transfer-encoding:") && line.contains("chunked")
+    });
+    if !chunked {
+        return Some((status, body.to_vec()));
+    }
+
+    let mut decoded = Vec::new();
+    let mut rest = body;
+    loop {
+        let size_end = rest.windows(2).position(|w| w == b"\r\n")?;
+        let size_str = std::str::from_utf8(&rest[..size_end]).ok()?;
+        let size = usize::from_str_radix(size_str.split(';').next()?.trim(), 16).ok()?;
+        if size == 0 {
+            break;
+        }
+
+        let start = size_end + 2;
+        decoded.extend_from_slice(rest.get(start..start + size)?);
+        rest = rest.get(start + size + 2..)?;
+    }
+
+    Some((status, decoded))
+}
+
+/// Percent-Encodes the Value for use in a Query-String
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
@@ -0,0 +1,152 @@
+//! Turns the Output of a libFuzzer based Fuzzer into Events
+
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    sync::mpsc,
+};
+
+use super::{Event, Stats};
+
+/// libFuzzer prints its Statistics for every new Input, which can be a lot of Lines, so they are
+/// only reported this often
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Reads the Output of a single Worker until it exits and reports its Statistics and Crashes
+pub(super) async fn report_output<R>(output: R, worker: usize, events: mpsc::UnboundedSender<Event>)
+where
+    R: AsyncRead + Unpin,
+{
+    let mut reader = BufReader::new(output);
+    let mut line = Vec::new();
+    let mut last_stats: Option<Instant> = None;
+
+    // The Output also contains the Inputs, so it can not be read as Lines of UTF-8
+    while let Ok(read) = reader.read_until(b'\n', &mut line).await {
+        if read == 0 {
+            break;
+        }
+
+        let event = parse_line(&String::from_utf8_lossy(&line), worker);
+        line.clear();
+
+        let event = match event {
+            Some(e) => e,
+            None => continue,
+        };
+        if let Event::Stats(_) = event {
+            if matches!(last_stats, Some(last) if last.elapsed() < STATS_INTERVAL) {
+                continue;
+            }
+            last_stats = Some(Instant::now());
+        }
+
+        let _ = events.send(event);
+    }
+}
+
+/// Parses a single Line of Output, like
+/// `#4096    pulse  cov: 12 ft: 13 corp: 5/20b lim: 43 exec/s: 2048 rss: 30Mb`
+fn parse_line(line: &str, worker: usize) -> Option<Event> {
+    if let Some((_, path)) = line.split_once("Test unit written to ") {
+        let name = Path::new(path.trim())
+            .file_name()?
+            .to_string_lossy()
+            .to_string();
+        return Some(Event::Artifact { name });
+    }
+
+    let mut tokens = line.strip_prefix('#')?.split_whitespace();
+    let mut stats = Stats {
+        worker,
+        execs: tokens.next()?.parse().ok()?,
+        ..Default::default()
+    };
+
+    while let Some(key) = tokens.next() {
+        let field = match key {
+            "cov:" => &mut stats.coverage,
+            "corp:" => &mut stats.corpus,
+            "exec/s:" => &mut stats.execs_per_sec,
+            "rss:" => &mut stats.rss_mb,
+            _ => continue,
+        };
+
+        // Some Values have a Suffix, like the Unit or the Size of the Corpus
+        let digits: String = tokens
+            .next()?
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        *field = digits.parse().ok()?;
+    }
+
+    Some(Event::Stats(stats))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats() {
+        assert_eq!(
+            Some(Event::Stats(Stats {
+                worker: 2,
+                execs: 4096,
+                execs_per_sec: 2048,
+                coverage: 12,
+                corpus: 5,
+                rss_mb: 30,
+            })),
+            parse_line(
+                "#4096\tpulse  cov: 12 ft: 13 corp: 5/20b lim: 43 exec/s: 2048 rss: 30Mb\n",
+                2
+            )
+        );
+        assert_eq!(
+            Some(Event::Stats(Stats {
+                worker: 0,
+                execs: 2,
+                execs_per_sec: 0,
+                coverage: 3,
+                corpus: 1,
+                rss_mb: 27,
+            })),
+            parse_line(
+                "#2\tINITED cov: 3 ft: 4 corp: 1/1b exec/s: 0 rss: 27Mb\n",
+                0
+            )
+        );
+    }
+
+    #[test]
+    fn artifact() {
+        assert_eq!(
+            Some(Event::Artifact {
+                name: "crash-da39a3ee5e6b4b0d3255bfef95601890afd80709".to_string()
+            }),
+            parse_line(
+                "artifact_prefix='/repo/fuzz/artifacts/parse/'; Test unit written to /repo/fuzz/artifacts/parse/crash-da39a3ee5e6b4b0d3255bfef95601890afd80709\n",
+                0
+            )
+        );
+    }
+
+    #[test]
+    fn other_lines() {
+        for line in [
+            "INFO: Seed: 1608565063\n",
+            "INFO: Loaded 1 modules   (1234 inline 8-bit counters): 1234 [0x1, 0x2)\n",
+            "==1234== ERROR: libFuzzer: deadly signal\n",
+            "#\n",
+            "\n",
+        ] {
+            assert_eq!(None, parse_line(line, 0), "{:?}", line);
+        }
+    }
+}
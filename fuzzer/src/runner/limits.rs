@@ -0,0 +1,254 @@
+//! Enforces the Resource-Limits of a Target for the Fuzzer Processes of a Run
+//!
+//! Memory and CPU are limited using a cgroup v2 per Run, if the Runner was configured with a
+//! cgroup that was delegated to it. Otherwise only the Memory can be limited using an rlimit on
+//! the Address-Space of every Process, which is not possible for Targets built with a Sanitizer,
+//! as those reserve huge amounts of virtual Memory upfront, and Runs of Targets with a CPU-Limit
+//! fail right away. The Disk-Usage of the Workspace is checked periodically while fuzzing.
+
+use std::{
+    ffi::CString,
+    os::unix::{ffi::OsStrExt, process::CommandExt},
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+use crate::project::{Limits, RunTarget, Sanitizer};
+
+use super::Limit;
+
+/// The Period in Microseconds used for the CPU-Quota
+const CPU_PERIOD: u64 = 100_000;
+/// The Address-Space also contains Mappings that are never backed by actual Memory, like the
+/// Binary itself, so the rlimit gets some Headroom on top of the configured Limit
+const ADDRESS_SPACE_HEADROOM: u64 = 1024 * 1024 * 1024;
+/// The Disk-Usage is only checked every couple of Seconds, as walking the Workspace is expensive
+const DISK_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A cgroup v2 that contains all the Fuzzer Processes of a single Run
+pub(super) struct Cgroup {
+    path: PathBuf,
+}
+
+impl Cgroup {
+    /// Creates the cgroup for the Run below the given Root, which needs to have the memory and cpu
+    /// Controllers enabled for its Children.
+    ///
+    /// The Memory-Limit applies to every Process, so the cgroup allows for the Limit of all of them
+    /// combined
+    pub fn create(root: &Path, name: &str, limits: &Limits, processes: usize) -> Option<Self> {
+        let path = root.join(name.replace('/', "-"));
+        if let Err(e) = std::fs::create_dir_all(&path) {
+            println!("Creating cgroup: {}", e);
+            return None;
+        }
+        let cgroup = Self { path };
+
+        if let Some(memory) = limits.memory_mb {
+            let bytes = memory * 1024 * 1024 * processes as u64;
+            cgroup.write("memory.max", &bytes.to_string()).ok()?;
+            // Swap is not always accounted for, in which case there is nothing to disable
+            let _ = std::fs::write(cgroup.path.join("memory.swap.max"), "0");
+        }
+        if let Some(cpus) = limits.cpus {
+            let quota = ((cpus * CPU_PERIOD as f64) as u64).max(1000);
+            cgroup
+                .write("cpu.max", &format!("{} {}", quota, CPU_PERIOD))
+                .ok()?;
+        }
+
+        Some(cgroup)
+    }
+
+    fn write(&self, file: &str, value: &str) -> std::io::Result<()> {
+        std::fs::write(self.path.join(file), value).map_err(|e| {
+            println!("Configuring cgroup {}: {}", file, e);
+            e
+        })
+    }
+
+    /// Moves the Process into the cgroup right before it executes the Command, so that everything
+    /// it spawns is also contained in it
+    pub fn attach(&self, cmd: &mut Command) {
+        let procs = CString::new(self.path.join("cgroup.procs").as_os_str().as_bytes())
+            .expect("cgroup Paths never contain a 0 Byte");
+
+        // SAFETY: The Closure only uses async-signal-safe Functions and does not allocate
+        unsafe {
+            cmd.pre_exec(move || {
+                let fd = libc::open(procs.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+                if fd < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+
+                // Writing 0 moves the writing Process itself
+                let written = libc::write(fd, b"0".as_ptr() as *const libc::c_void, 1);
+                libc::close(fd);
+                if written < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+
+                Ok(())
+            });
+        }
+    }
+
+    /// If any Process of the cgroup was killed, because the Memory-Limit was reached
+    pub fn oom_killed(&self) -> bool {
+        std::fs::read_to_string(self.path.join("memory.events"))
+            .map(|events| {
+                events.lines().any(|line| {
+                    line.strip_prefix("oom_kill ")
+                        .and_then(|count| count.trim().parse::<u64>().ok())
+                        .map(|count| count > 0)
+                        .unwrap_or(false)
+                })
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl Drop for Cgroup {
+    fn drop(&mut self) {
+        // Makes sure there are no Processes left over, otherwise the cgroup can not be removed
+        let _ = std::fs::write(self.path.join("cgroup.kill"), "1");
+
+        if let Err(e) = std::fs::remove_dir(&self.path) {
+            println!("Removing cgroup: {}", e);
+        }
+    }
+}
+
+/// Checks if the Limits can be enforced at all, as there is no Fallback for the CPU-Limit without
+/// a cgroup
+pub(super) fn check(limits: &Limits, cgroup: bool) -> Result<(), String> {
+    if limits.cpus.is_some() && !cgroup {
+        return Err("The CPU-Limit can only be enforced using a cgroup".to_string());
+    }
+
+    Ok(())
+}
+
+/// Applies the Limits to a single Fuzzer Process, either by adding it to the cgroup of the Run or
+/// by falling back to rlimits
+pub(super) fn apply(
+    cmd: &mut Command,
+    cgroup: Option<&Cgroup>,
+    limits: &Limits,
+    config: &RunTarget,
+) {
+    if let Some(cgroup) = cgroup {
+        cgroup.attach(cmd);
+        return;
+    }
+
+    let memory = match limits.memory_mb {
+        Some(m) => m,
+        None => return,
+    };
+    if sanitized(config) {
+        println!("The Memory-Limit of sanitized Targets can only be enforced using a cgroup");
+        return;
+    }
+
+    let bytes = memory * 1024 * 1024 + ADDRESS_SPACE_HEADROOM;
+    // SAFETY: setrlimit is async-signal-safe
+    unsafe {
+        cmd.pre_exec(move || {
+            let limit = libc::rlimit {
+                rlim_cur: bytes,
+                rlim_max: bytes,
+            };
+            if libc::setrlimit(libc::RLIMIT_AS, &limit) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            Ok(())
+        });
+    }
+}
+
+/// If the Fuzzer might be built with a Sanitizer, cargo-fuzz uses the AddressSanitizer by default
+fn sanitized(config: &RunTarget) -> bool {
+    match config {
+        RunTarget::CargoFuzz { options, .. } => options.sanitizer != Some(Sanitizer::None),
+        _ => false,
+    }
+}
+
+/// If the Fuzzer itself detected that the Target used too much Memory
+pub(super) fn fuzzer_out_of_memory(project_path: &Path, config: &RunTarget) -> bool {
+    match config {
+        // libFuzzer stores the Inputs that exceeded the rss Limit with an "oom-" Prefix
+        RunTarget::CargoFuzz { name, .. } => {
+            std::fs::read_dir(project_path.join("fuzz").join("artifacts").join(name))
+                .map(|entries| {
+                    entries
+                        .filter_map(|e| e.ok())
+                        .any(|e| e.file_name().to_string_lossy().starts_with("oom-"))
+                })
+                .unwrap_or(false)
+        }
+        _ => false,
+    }
+}
+
+/// Periodically checks the Disk-Usage of the Workspace of a Run
+pub(super) struct DiskWatch {
+    paths: Vec<PathBuf>,
+    limit: Option<u64>,
+}
+
+impl DiskWatch {
+    pub fn new(paths: &[&Path], limits: &Limits) -> Self {
+        Self {
+            paths: paths.iter().map(|p| p.to_path_buf()).collect(),
+            limit: limits.disk_mb.map(|mb| mb * 1024 * 1024),
+        }
+    }
+
+    /// Resolves once the Workspace exceeds the Limit, which never happens without a Limit
+    pub async fn exceeded(&self) -> Limit {
+        let limit = match self.limit {
+            Some(l) => l,
+            None => std::future::pending().await,
+        };
+
+        loop {
+            tokio::time::sleep(DISK_CHECK_INTERVAL).await;
+
+            let paths = self.paths.clone();
+            let used = tokio::task::spawn_blocking(move || {
+                paths.iter().map(|p| disk_usage(p)).sum::<u64>()
+            })
+            .await
+            .unwrap_or(0);
+
+            if used > limit {
+                println!("Workspace uses {} Bytes, exceeding the Limit", used);
+                return Limit::Disk;
+            }
+        }
+    }
+}
+
+/// The Size of all the Files in the Folder
+fn disk_usage(path: &Path) -> u64 {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(m) => m,
+        Err(_) => return 0,
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| disk_usage(&e.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
@@ -1,131 +1,719 @@
-use std::path::PathBuf;
+use std::{
+    future::Future,
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    pin::Pin,
+    process::{Command, Stdio},
+};
 
-use tokio::sync::oneshot;
+use async_trait::async_trait;
+use futures_util::future::select_all;
+use tokio::{
+    process::Child,
+    sync::{mpsc, oneshot},
+};
 
-use crate::{project::RunTarget, FuzzTarget, Source};
+use crate::{
+    manifest::{self, Manifest},
+    project::{CargoFuzzOptions, RunTarget, Target},
+    FuzzTarget, Source,
+};
 
-use super::Runner;
+use super::{
+    canceled, libfuzzer,
+    limits::{self, Cgroup, DiskWatch},
+    Event, Limit, RunOutput, Runner, Stop,
+};
+
+/// The Environment Variable that contains the Path of the shared Corpus for Command Targets
+pub const CORPUS_ENV: &str = "CFUZZ_CORPUS";
+
+/// The cancel Signal of a Run, which can be awaited multiple times until it fired
+pub(super) type Cancel = Pin<Box<dyn Future<Output = Stop> + Send>>;
+
+/// The different Phases of a single Run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Obtaining the Source of the Project
+    Checkout,
+    /// Building the Fuzzer
+    Build,
+    /// Actually running the Fuzzer
+    Fuzz,
+}
+
+/// A Launcher controls how the Commands of the ProcessRunner are actually executed, which allows
+/// for running them in some restricted Environment
+pub trait Launcher {
+    /// Turns the Command for the given Phase into the Command that should actually be spawned.
+    ///
+    /// The writable Paths are the only ones the Command needs to modify
+    fn launch(&self, phase: Phase, writable: &[&Path], cmd: Command) -> std::io::Result<Command>;
+}
+
+/// The default Launcher that simply runs all the Commands directly
+pub struct Direct;
+
+impl Launcher for Direct {
+    fn launch(&self, _: Phase, _: &[&Path], cmd: Command) -> std::io::Result<Command> {
+        Ok(cmd)
+    }
+}
 
 pub struct ProcessRunner {
     subfolder: PathBuf,
+    launcher: Box<dyn Launcher + Send + Sync>,
+    cgroup: Option<PathBuf>,
 }
 
 impl ProcessRunner {
     pub fn new<P>(path: P) -> Self
     where
         P: Into<PathBuf>,
+    {
+        Self::with_launcher(path, Direct)
+    }
+
+    /// Creates a new ProcessRunner that uses the given Launcher to execute all of its Commands
+    pub fn with_launcher<P, L>(path: P, launcher: L) -> Self
+    where
+        P: Into<PathBuf>,
+        L: Launcher + Send + Sync + 'static,
     {
         Self {
             subfolder: path.into(),
+            launcher: Box::new(launcher),
+            cgroup: None,
         }
     }
 
-    fn setup(&self, pname: &str, name: &str, source: &Source) -> (PathBuf, Box<dyn FnOnce()>) {
-        let project_path = self.subfolder.join(pname);
+    /// Enforce the Memory- and CPU-Limits of the Targets using cgroups created below the given
+    /// cgroup, which needs to be delegated to the current User
+    pub fn with_cgroup<P>(mut self, root: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.cgroup = Some(root.into());
+        self
+    }
 
-        match source {
-            Source::Git { repo } => {
-                let repo_path = project_path.join(name);
-                let repo_path_str = repo_path.to_str().unwrap();
+    async fn run(
+        &self,
+        target: &FuzzTarget,
+        repo_dir: &Path,
+        corpus_dir: &Path,
+        events: &mpsc::UnboundedSender<Event>,
+        cancel: &mut Cancel,
+    ) -> Option<RunOutput> {
+        let run_id = format!("{}/{}", target.project_name(), target.name());
+        let project_path = repo_dir.join(&target.runner().folder);
+        let config = &target.runner().target;
+        let limits = &target.runner().limits;
+        let writable = &[repo_dir, corpus_dir];
 
-                let result = std::process::Command::new("git")
-                    .arg("clone")
-                    .arg(repo)
-                    .arg(repo_path_str)
-                    .output()
-                    .unwrap();
+        if let Err(e) = limits::check(limits, self.cgroup.is_some()) {
+            println!("{}", e);
+            return None;
+        }
 
-                // TODO
-                let _ = result;
+        let _ = events.send(Event::Building);
+        self.build(&project_path, config, writable, cancel).await?;
 
-                let cleanup_path = repo_path.clone();
-                let cleanup = move || {
-                    std::fs::remove_dir_all(cleanup_path).unwrap();
-                };
+        let processes = process_count(config, target.runner().workers);
 
-                (repo_path, Box::new(cleanup))
+        let cgroup = match &self.cgroup {
+            // Falling back to rlimits would silently ignore the CPU-Limit
+            Some(root) if limits.memory_mb.is_some() || limits.cpus.is_some() => {
+                Some(Cgroup::create(root, &run_id, limits, processes)?)
             }
+            _ => None,
+        };
+
+        let mut children = Vec::with_capacity(processes);
+        for worker in 0..processes {
+            let cmd = fuzz_command(&project_path, target.runner(), corpus_dir)?;
+            let cmd = self.launcher.launch(Phase::Fuzz, writable, cmd);
+            let child = cmd.and_then(|mut cmd| {
+                limits::apply(&mut cmd, cgroup.as_ref(), limits, config);
+                spawn(cmd, Stdio::piped())
+            });
+
+            match child {
+                Ok(mut c) => {
+                    if let Some(stderr) = c.stderr.take() {
+                        tokio::spawn(libfuzzer::report_output(stderr, worker, events.clone()));
+                    }
+                    children.push(c);
+                }
+                Err(e) => {
+                    println!("Spawning Fuzzer: {}", e);
+                    kill_children(&mut children).await;
+                    return None;
+                }
+            };
+        }
+
+        if let Some(state) = crate::STATE.get() {
+            let pids = children.iter().filter_map(|c| c.id()).collect();
+            state.processes.lock().unwrap().insert(run_id.clone(), pids);
+        }
+        let _ = events.send(Event::Fuzzing { workers: processes });
+
+        let disk = DiskWatch::new(writable, limits);
+        let result = wait_children(&mut children, cancel, disk.exceeded()).await;
+
+        if let Some(state) = crate::STATE.get() {
+            state.processes.lock().unwrap().remove(&run_id);
         }
+
+        let exceeded = match result? {
+            Some(limit) => Some(limit),
+            None if cgroup.map(|c| c.oom_killed()).unwrap_or(false)
+                || limits::fuzzer_out_of_memory(&project_path, config) =>
+            {
+                Some(Limit::Memory)
+            }
+            None => None,
+        };
+
+        Some(RunOutput {
+            artifacts: target_artifacts(&project_path, config)?,
+            exceeded,
+        })
     }
 
-    fn run(
+    /// Builds the Fuzzer and performs all the other Steps needed before it can be started
+    async fn build(
         &self,
-        project_path: PathBuf,
+        project_path: &Path,
         config: &RunTarget,
-        mut cancel: oneshot::Receiver<()>,
-    ) -> Option<Vec<Vec<u8>>> {
-        match config {
-            RunTarget::CargoFuzz { name } => {
-                let artifacts_path = project_path.join("fuzz").join("artifacts").join(name);
-
-                let output = std::process::Command::new("cargo")
-                    .current_dir(project_path)
-                    .arg("fuzz")
-                    .arg("run")
-                    .arg(name)
-                    .stdout(std::process::Stdio::null())
-                    .stderr(std::process::Stdio::null())
-                    .spawn();
-
-                let mut child = match output {
-                    Ok(c) => c,
-                    Err(_) => {
-                        todo!()
-                    }
-                };
+        writable: &[&Path],
+        cancel: &mut Cancel,
+    ) -> Option<()> {
+        prepare_folders(project_path, config).ok()?;
 
-                loop {
-                    // println!("Waiting for Child");
+        let cmd = match build_command(project_path, config) {
+            Some(c) => c,
+            None => return Some(()),
+        };
 
-                    // If the child is done, we exit
-                    if child.try_wait().unwrap().is_some() {
-                        println!("Child Done");
-                        break;
-                    }
-                    // If we received a signal to cancel the Run, we kill the Child and exit
-                    if cancel.try_recv().is_ok() {
-                        child.kill().unwrap();
-                        return None;
-                    }
+        let cmd = self.launcher.launch(Phase::Build, writable, cmd);
+        let mut child = match cmd.and_then(|cmd| spawn(cmd, Stdio::null())) {
+            Ok(c) => c,
+            Err(e) => {
+                println!("Spawning Build: {}", e);
+                return None;
+            }
+        };
+
+        let status = tokio::select! {
+            status = child.wait() => status.ok()?,
+            _ = cancel => {
+                kill_children(std::slice::from_mut(&mut child)).await;
+                return None;
+            }
+        };
 
-                    // Otherwise we wait a second before polling again
-                    std::thread::sleep(std::time::Duration::from_secs(1));
+        if !status.success() {
+            println!("Building Target failed: {}", status);
+            return None;
+        }
+
+        Some(())
+    }
+}
+
+/// Checks out the Source of the Project and returns the Path to it alongside a Function to clean
+/// it up again.
+///
+/// If the Repository contains a [`Manifest`], the Targets of the Project are synced with it and
+/// the Target of the Run is replaced with its current Definition
+pub(super) async fn setup(
+    subfolder: &Path,
+    launcher: &(dyn Launcher + Sync),
+    target: &mut FuzzTarget,
+) -> Option<(PathBuf, Box<dyn FnOnce() + Send>)> {
+    let project_path = subfolder.join(target.project_name());
+
+    let (repo_path, cleanup) = match target.config() {
+        Source::Git { repo } => {
+            let repo_path = project_path.join(target.name());
+
+            // A previous Run that was interrupted can leave its checkout behind
+            if repo_path.exists() {
+                if let Err(e) = std::fs::remove_dir_all(&repo_path) {
+                    println!("Removing old Checkout {}: {}", repo_path.display(), e);
+                    return None;
                 }
+            }
+
+            // The checkout itself does not exist yet, so the Project Folder needs to be writable
+            if let Err(e) = std::fs::create_dir_all(&project_path) {
+                println!("Creating Project Folder: {}", e);
+                return None;
+            }
 
-                let entry_iter = std::fs::read_dir(artifacts_path)
-                    .ok()?
-                    .filter_map(|e| e.ok())
-                    .filter_map(|e| {
-                        let file_type = e.file_type().ok()?;
-                        if file_type.is_dir() {
-                            return None;
-                        }
+            let mut cmd = Command::new("git");
+            cmd.arg("clone").arg(repo).arg(&repo_path);
 
-                        Some(e.path())
-                    });
+            let output = match launcher.launch(Phase::Checkout, &[&project_path], cmd) {
+                Ok(cmd) => tokio::process::Command::from(cmd).output().await,
+                Err(e) => Err(e),
+            };
+            match output {
+                Ok(o) if o.status.success() => {}
+                Ok(o) => {
+                    println!(
+                        "Cloning {} failed: {}",
+                        repo,
+                        String::from_utf8_lossy(&o.stderr).trim()
+                    );
+                    return None;
+                }
+                Err(e) => {
+                    println!("Cloning {}: {}", repo, e);
+                    return None;
+                }
+            };
 
-                let results = entry_iter
-                    .filter_map(|path| std::fs::read(path).ok())
-                    .collect();
+            let cleanup_path = repo_path.clone();
+            let cleanup = move || {
+                if let Err(e) = std::fs::remove_dir_all(&cleanup_path) {
+                    println!("Removing Checkout {}: {}", cleanup_path.display(), e);
+                }
+            };
+
+            (repo_path, Box::new(cleanup) as Box<dyn FnOnce() + Send>)
+        }
+    };
 
-                Some(results)
+    match Manifest::load(&repo_path) {
+        Ok(Some(manifest)) => {
+            if let Err(e) = manifest.sync(target.project_name()).await {
+                println!("Syncing the Targets of the {}: {}", manifest::FILE, e);
             }
+
+            match manifest.target(target.runner()) {
+                Some(current) => target.set_runner(current),
+                None => println!("Target is not part of the {}", manifest::FILE),
+            };
+        }
+        Ok(None) => {}
+        Err(e) => println!("Invalid Manifest: {}", e),
+    };
+
+    Some((repo_path, cleanup))
+}
+
+/// The Corpus is stored outside of the checkout, so it is kept between Runs and shared by all the
+/// Runs of the same Target
+pub(super) fn shared_corpus(subfolder: &Path, target: &FuzzTarget) -> PathBuf {
+    let corpus_dir = subfolder
+        .join(target.project_name())
+        .join(".corpus")
+        .join(&target.runner().name);
+    if let Err(e) = std::fs::create_dir_all(&corpus_dir) {
+        println!("Creating Corpus Folder: {}", e);
+    }
+
+    // The Fuzzer runs in a different working directory, so it needs the absolute Path
+    std::fs::canonicalize(&corpus_dir).unwrap_or(corpus_dir)
+}
+
+/// Creates the Folders upfront, as not every Fuzzer will create them on its own
+pub(super) fn prepare_folders(project_path: &Path, config: &RunTarget) -> std::io::Result<()> {
+    if let RunTarget::Command {
+        artifacts_dir,
+        corpus_dir,
+        ..
+    } = config
+    {
+        std::fs::create_dir_all(project_path.join(artifacts_dir))?;
+        if let Some(corpus_dir) = corpus_dir {
+            std::fs::create_dir_all(project_path.join(corpus_dir))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Builds the Command that builds the Fuzzer for the Target, if it needs to be built separately
+pub(super) fn build_command(project_path: &Path, config: &RunTarget) -> Option<Command> {
+    match config {
+        RunTarget::CargoFuzz { name, options } => {
+            let mut cmd = cargo_fuzz_command(project_path, "build", options);
+            cmd.arg(name);
+            Some(cmd)
+        }
+        RunTarget::Honggfuzz { .. } => {
+            let mut cmd = Command::new("cargo");
+            cmd.current_dir(project_path).arg("hfuzz").arg("build");
+            Some(cmd)
+        }
+        RunTarget::Command { build, env, .. } => {
+            let mut cmd = Command::new("sh");
+            cmd.current_dir(project_path)
+                .arg("-c")
+                .arg(build.as_ref()?)
+                .envs(env);
+            Some(cmd)
+        }
+    }
+}
+
+/// The Number of Fuzzer Processes that need to be started for the given Number of Workers.
+///
+/// Honggfuzz already manages multiple Threads on its own, while all the other Fuzzers are simply
+/// started multiple times on the same Corpus
+pub(super) fn process_count(config: &RunTarget, workers: usize) -> usize {
+    match config {
+        RunTarget::Honggfuzz { .. } => 1,
+        _ => workers.max(1),
+    }
+}
+
+/// Loads all the Crashes found by the Fuzzer for the Target
+pub(super) fn target_artifacts(project_path: &Path, config: &RunTarget) -> Option<Vec<Vec<u8>>> {
+    match config {
+        RunTarget::CargoFuzz { name, .. } => collect_artifacts(
+            project_path.join("fuzz").join("artifacts").join(name),
+            |_| true,
+        ),
+        RunTarget::Honggfuzz { name } => {
+            collect_artifacts(project_path.join("hfuzz_workspace").join(name), |path| {
+                path.extension().map(|e| e == "fuzz").unwrap_or(false)
+            })
+        }
+        RunTarget::Command { artifacts_dir, .. } => {
+            collect_artifacts(project_path.join(artifacts_dir), |_| true)
         }
     }
 }
 
+/// Builds the Command to start a single Fuzzer Process for the Target
+pub(super) fn fuzz_command(
+    project_path: &Path,
+    target: &Target,
+    corpus_dir: &Path,
+) -> Option<Command> {
+    let workers = target.workers;
+    let memory = target.limits.memory_mb;
+
+    match &target.target {
+        RunTarget::CargoFuzz { name, options } => {
+            let mut cmd = cargo_fuzz_command(project_path, "run", options);
+            if let Some(jobs) = options.jobs {
+                cmd.arg("--jobs").arg(jobs.to_string());
+            }
+            cmd.arg(name);
+
+            // libFuzzer stores new Inputs in the first Corpus, so the shared one needs to come
+            // first while the Corpus from the Repository is still used as a Seed
+            cmd.arg(corpus_dir);
+            let repo_corpus = Path::new("fuzz").join("corpus").join(name);
+            if project_path.join(&repo_corpus).is_dir() {
+                cmd.arg(repo_corpus);
+            }
+
+            // Everything after this is passed to libFuzzer itself
+            cmd.arg("--");
+            if let Some(max_len) = options.max_len {
+                cmd.arg(format!("-max_len={}", max_len));
+            }
+            if let Some(dict) = &options.dict {
+                cmd.arg(format!("-dict={}", dict));
+            }
+            if options.only_ascii {
+                cmd.arg("-only_ascii=1");
+            }
+            if let Some(memory) = memory {
+                cmd.arg(format!("-rss_limit_mb={}", memory));
+            }
+            cmd.args(&options.args);
+
+            Some(cmd)
+        }
+        RunTarget::Honggfuzz { name } => {
+            // Stop after the first Crash, like libFuzzer does, so that repeating Targets
+            // behave the same for both Fuzzers
+            let mut args = format!("--exit_upon_crash --threads {}", workers.max(1));
+            if let Some(memory) = memory {
+                args.push_str(&format!(" --rlimit_rss {}", memory));
+            }
+
+            let mut cmd = Command::new("cargo");
+            cmd.current_dir(project_path)
+                .arg("hfuzz")
+                .arg("run")
+                .arg(name)
+                .env("HFUZZ_RUN_ARGS", args);
+
+            Some(cmd)
+        }
+        RunTarget::Command { run, env, .. } => {
+            let (program, args) = run.split_first()?;
+
+            let mut cmd = Command::new(program);
+            cmd.current_dir(project_path)
+                .args(args)
+                .envs(env)
+                .env(CORPUS_ENV, corpus_dir);
+
+            Some(cmd)
+        }
+    }
+}
+
+/// Builds the `cargo fuzz` Command for the Subcommand, including all the Options that affect how
+/// the Target is built, so that `build` and `run` always agree on them
+fn cargo_fuzz_command(
+    project_path: &Path,
+    subcommand: &str,
+    options: &CargoFuzzOptions,
+) -> Command {
+    let mut cmd = Command::new("cargo");
+    cmd.current_dir(project_path);
+
+    if let Some(toolchain) = &options.toolchain {
+        cmd.arg(format!("+{}", toolchain));
+    }
+
+    cmd.arg("fuzz").arg(subcommand);
+    if let Some(sanitizer) = &options.sanitizer {
+        cmd.arg("--sanitizer").arg(sanitizer.as_str());
+    }
+    if options.release {
+        cmd.arg("--release");
+    }
+    if options.debug_assertions {
+        cmd.arg("--debug-assertions");
+    }
+
+    cmd
+}
+
+/// Spawns the Command in its own Process-Group, so we can also kill all of the Processes it
+/// spawned itself, like the Fuzzer started by cargo
+fn spawn(mut cmd: Command, stderr: Stdio) -> std::io::Result<Child> {
+    cmd.stdout(Stdio::null()).stderr(stderr).process_group(0);
+
+    tokio::process::Command::from(cmd).spawn()
+}
+
+/// Waits for the first Child to exit, until a cancel signal was received or until the Watch
+/// reports an exceeded Limit and then kills all the remaining Children.
+///
+/// Returns None if the Run was canceled, running out of Budget still completes the Run
+async fn wait_children<W>(
+    children: &mut [Child],
+    cancel: &mut Cancel,
+    watch: W,
+) -> Option<Option<Limit>>
+where
+    W: Future<Output = Limit>,
+{
+    let result = {
+        let exits = children.iter_mut().map(|c| Box::pin(c.wait()));
+
+        tokio::select! {
+            // If any child is done, the Run is done as well
+            _ = select_all(exits) => {
+                println!("Child Done");
+                Some(None)
+            }
+            // If we received a signal to stop the Run, we kill the Children and only keep the
+            // Results if the Run simply used up its Budget
+            stop = cancel => match stop {
+                Stop::Budget => Some(None),
+                Stop::Cancel => None,
+            },
+            limit = watch => Some(Some(limit)),
+        }
+    };
+
+    kill_children(children).await;
+
+    result
+}
+
+/// Kills the Process-Groups of all the Children and waits for them to exit
+async fn kill_children(children: &mut [Child]) {
+    for child in children.iter_mut() {
+        let pid = match child.id() {
+            Some(pid) => pid,
+            // The Child was already awaited
+            None => continue,
+        };
+        if child.try_wait().ok().flatten().is_some() {
+            continue;
+        }
+
+        // SAFETY: killpg has no memory safety requirements, at worst the Group no longer exists
+        unsafe {
+            libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+        }
+        let _ = child.wait().await;
+    }
+}
+
+/// Reads all the Files in the given Folder that match the Filter
+fn collect_artifacts<F>(path: PathBuf, filter: F) -> Option<Vec<Vec<u8>>>
+where
+    F: Fn(&Path) -> bool,
+{
+    let results = std::fs::read_dir(path)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let file_type = e.file_type().ok()?;
+            if file_type.is_dir() {
+                return None;
+            }
+
+            Some(e.path())
+        })
+        .filter(|path| filter(path))
+        .filter_map(|path| std::fs::read(path).ok())
+        .collect();
+
+    Some(results)
+}
+
+#[async_trait]
 impl Runner for ProcessRunner {
-    fn run(&self, target: FuzzTarget, cancel: oneshot::Receiver<()>) -> Option<Vec<Vec<u8>>> {
-        let (repo_dir, cleanup) = self.setup(target.project_name(), target.name(), target.config());
+    async fn run(
+        &self,
+        mut target: FuzzTarget,
+        events: mpsc::UnboundedSender<Event>,
+        cancel: oneshot::Receiver<Stop>,
+    ) -> Option<RunOutput> {
+        let _ = events.send(Event::Cloning);
+        let (repo_dir, cleanup) =
+            setup(&self.subfolder, self.launcher.as_ref(), &mut target).await?;
+
+        let corpus_dir = shared_corpus(&self.subfolder, &target);
 
-        let result = self.run(
-            repo_dir.join(&target.runner().folder),
-            &target.runner().target,
-            cancel,
-        );
+        let mut cancel: Cancel = Box::pin(canceled(cancel));
+        let result = self
+            .run(&target, &repo_dir, &corpus_dir, &events, &mut cancel)
+            .await;
 
-        cleanup();
+        let _ = tokio::task::spawn_blocking(cleanup).await;
 
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use crate::project::Limits;
+
+    use super::*;
+
+    fn command_target(run: &[&str]) -> Target {
+        Target {
+            name: "command".to_string(),
+            folder: ".".to_string(),
+            target: RunTarget::Command {
+                build: None,
+                run: run.iter().map(|a| a.to_string()).collect(),
+                artifacts_dir: "crashes".to_string(),
+                corpus_dir: None,
+                env: HashMap::new(),
+            },
+            repeating: false,
+            sanitizers: Vec::new(),
+            workers: 1,
+            limits: Limits::default(),
+            budget_secs: None,
+            interval_secs: None,
+        }
+    }
+
+    #[test]
+    fn dict_relative_to_target() {
+        let mut target = command_target(&[]);
+        target.target = RunTarget::CargoFuzz {
+            name: "parse".to_string(),
+            options: CargoFuzzOptions {
+                dict: Some("fuzz/parse.dict".to_string()),
+                ..Default::default()
+            },
+        };
+
+        // The Fuzzer already runs in the Target Folder, so the Path is passed on unchanged
+        let cmd = fuzz_command(Path::new("workspace/repo"), &target, Path::new("/corpus")).unwrap();
+        let args: Vec<_> = cmd.get_args().collect();
+        assert!(args.contains(&std::ffi::OsStr::new("-dict=fuzz/parse.dict")));
+        assert_eq!(Some(Path::new("workspace/repo")), cmd.get_current_dir());
+    }
+
+    #[test]
+    fn command_receives_corpus() {
+        let target = command_target(&["sh", "-c", "printf %s \"$CFUZZ_CORPUS\""]);
+        let mut cmd = fuzz_command(Path::new("."), &target, Path::new("/corpus")).unwrap();
+
+        let output = cmd.output().unwrap();
+        assert!(output.status.success());
+        assert_eq!("/corpus", String::from_utf8(output.stdout).unwrap());
+    }
+
+    #[tokio::test]
+    async fn cpu_limit_needs_cgroup() {
+        let dir = tempfile::tempdir().unwrap();
+        let corpus = dir.path().join("corpus");
+
+        let mut target = command_target(&["sh", "-c", "mkdir -p crashes"]);
+        target.limits.cpus = Some(1.0);
+        let source = Source::Git {
+            repo: "repo".to_string(),
+        };
+        let target = FuzzTarget::new("project", "command", target, source);
+
+        let (events, mut recv) = mpsc::unbounded_channel();
+        let mut cancel: Cancel = Box::pin(std::future::pending());
+        let output = ProcessRunner::new(dir.path())
+            .run(&target, dir.path(), &corpus, &events, &mut cancel)
+            .await;
+
+        assert_eq!(None, output);
+        assert!(recv.try_recv().is_err());
+    }
+
+    /// Runs the Command Target until the cancel Signal is sent with the given Reason
+    async fn stopped_run(stop: Stop) -> Option<RunOutput> {
+        let dir = tempfile::tempdir().unwrap();
+        let corpus = dir.path().join("corpus");
+
+        let target = command_target(&[
+            "sh",
+            "-c",
+            "mkdir -p crashes && echo crash > crashes/crash-1 && sleep 60",
+        ]);
+        let source = Source::Git {
+            repo: "repo".to_string(),
+        };
+        let target = FuzzTarget::new("project", "command", target, source);
+
+        let (events, _recv) = mpsc::unbounded_channel();
+        let mut cancel: Cancel = Box::pin(async move {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            stop
+        });
+
+        ProcessRunner::new(dir.path())
+            .run(&target, dir.path(), &corpus, &events, &mut cancel)
+            .await
+    }
+
+    #[tokio::test]
+    async fn budget_keeps_artifacts() {
+        let output = stopped_run(Stop::Budget).await.unwrap();
+        assert_eq!(vec![b"crash\n".to_vec()], output.artifacts);
+        assert_eq!(None, output.exceeded);
+
+        assert_eq!(None, stopped_run(Stop::Cancel).await);
+    }
+}
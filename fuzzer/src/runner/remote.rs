@@ -0,0 +1,672 @@
+//! Distributes the Targets to remote Agents
+//!
+//! The [`Coordinator`] keeps track of all the registered Agents and a Queue of Jobs. Agents
+//! register themselves over HTTP, periodically send a Heartbeat and pull new Jobs, which they
+//! then run using their own local Runner. While running they stream back their Logs and Events
+//! and once they are done, they upload all the found Artifacts.
+//!
+//! The [`RemoteRunner`] submits its Targets to the Coordinator and waits for an Agent to finish
+//! them, so from the perspective of the rest of the Program it behaves like any other Runner.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+use warp::{hyper::StatusCode, Filter};
+
+use crate::{
+    auth::{self, Identity},
+    now, FuzzTarget,
+};
+
+use super::{canceled, Event, Limit, RunOutput, Runner, Stop};
+
+/// Agents that did not send a Heartbeat for this long are considered dead
+const AGENT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The Registration send by an Agent when it starts
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Registration {
+    /// The human readable Name of the Agent
+    pub name: String,
+}
+
+/// The Response to a successful Registration
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Registered {
+    /// The ID the Agent should use for all further Requests
+    pub id: String,
+}
+
+/// The periodic Heartbeat of an Agent
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Heartbeat {
+    /// The Jobs currently running on the Agent
+    pub jobs: Vec<u64>,
+    /// Arbitrary Statistics about the Agent, like its Load
+    pub stats: serde_json::Value,
+}
+
+/// The Response to a Heartbeat
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HeartbeatResponse {
+    /// The Jobs the Agent should cancel
+    pub cancel: Vec<u64>,
+    /// The Jobs that used up their Budget, which the Agent should stop and then report as usual
+    #[serde(default)]
+    pub stop: Vec<u64>,
+}
+
+/// A Job for an Agent
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Job {
+    /// The ID of the Job
+    pub id: u64,
+    /// The Target that should be run
+    pub target: FuzzTarget,
+}
+
+/// Send by an Agent once it finished a Job
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JobFinished {
+    /// If the Job ran successfully
+    pub success: bool,
+    /// The Resource-Limit the Job exceeded
+    #[serde(default)]
+    pub exceeded: Option<Limit>,
+}
+
+/// The Information about a registered Agent
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentInfo {
+    /// The ID authenticates the Agent in its Requests, so it is never send out
+    #[serde(skip_serializing)]
+    pub id: String,
+    pub name: String,
+    /// The Time of the last Heartbeat in Seconds since the Unix-Epoch
+    pub last_seen: u64,
+    /// The Jobs currently running on the Agent
+    pub jobs: Vec<u64>,
+    /// The Statistics from the last Heartbeat
+    pub stats: serde_json::Value,
+}
+
+struct JobState {
+    target: Option<FuzzTarget>,
+    agent: Option<String>,
+    canceled: bool,
+    /// The Job used up its Budget
+    stopped: bool,
+    artifacts: Vec<Vec<u8>>,
+    /// The Events that were not yet passed on to the Runner
+    events: Vec<Event>,
+    finished: Option<bool>,
+    exceeded: Option<Limit>,
+}
+
+#[derive(Default)]
+struct Inner {
+    next_id: u64,
+    agents: HashMap<String, AgentInfo>,
+    queue: VecDeque<u64>,
+    jobs: HashMap<u64, JobState>,
+}
+
+impl Inner {
+    /// The Job, if it is assigned to the given Agent
+    fn assigned(&mut self, agent: &str, job: u64) -> Option<&mut JobState> {
+        self.jobs
+            .get_mut(&job)
+            .filter(|j| j.agent.as_deref() == Some(agent))
+    }
+}
+
+/// Keeps track of all the Agents and Jobs
+#[derive(Default)]
+pub struct Coordinator {
+    inner: Mutex<Inner>,
+}
+
+impl Coordinator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All the currently registered Agents
+    pub fn agents(&self) -> Vec<AgentInfo> {
+        self.inner
+            .lock()
+            .unwrap()
+            .agents
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    fn register(&self, registration: Registration) -> Registered {
+        let mut inner = self.inner.lock().unwrap();
+
+        // The ID is all that identifies the Agent in its Requests, so it must not be guessable
+        let id = auth::generate_token();
+        inner.agents.insert(
+            id.clone(),
+            AgentInfo {
+                id: id.clone(),
+                name: registration.name,
+                last_seen: now(),
+                jobs: Vec::new(),
+                stats: serde_json::Value::Null,
+            },
+        );
+
+        Registered { id }
+    }
+
+    fn heartbeat(&self, agent: &str, heartbeat: Heartbeat) -> Option<HeartbeatResponse> {
+        let mut guard = self.inner.lock().unwrap();
+        let inner = &mut *guard;
+
+        let info = inner.agents.get_mut(agent)?;
+        info.last_seen = now();
+        info.jobs = heartbeat.jobs;
+        info.stats = heartbeat.stats;
+
+        let cancel = info
+            .jobs
+            .iter()
+            .copied()
+            .filter(|id| inner.jobs.get(id).map(|j| j.canceled).unwrap_or(true))
+            .collect();
+        let stop = info
+            .jobs
+            .iter()
+            .copied()
+            .filter(|id| inner.jobs.get(id).map(|j| j.stopped).unwrap_or(false))
+            .collect();
+
+        Some(HeartbeatResponse { cancel, stop })
+    }
+
+    fn next_job(&self, agent: &str) -> Option<Option<Job>> {
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.agents.contains_key(agent) {
+            return None;
+        }
+
+        while let Some(id) = inner.queue.pop_front() {
+            let job = match inner.jobs.get_mut(&id) {
+                Some(j) if !j.canceled => j,
+                _ => continue,
+            };
+
+            let target = match job.target.take() {
+                Some(t) => t,
+                None => continue,
+            };
+            job.agent = Some(agent.to_string());
+
+            return Some(Some(Job { id, target }));
+        }
+
+        Some(None)
+    }
+
+    fn log(&self, agent: &str, job: u64, lines: Vec<String>) {
+        let inner = self.inner.lock().unwrap();
+        let name = inner
+            .agents
+            .get(agent)
+            .map(|a| a.name.as_str())
+            .unwrap_or(agent);
+
+        for line in lines {
+            println!("[{}/{}] {}", name, job, line);
+        }
+    }
+
+    fn artifact(&self, agent: &str, job: u64, artifact: Vec<u8>) -> Option<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.assigned(agent, job)?.artifacts.push(artifact);
+        Some(())
+    }
+
+    fn events(&self, agent: &str, job: u64, events: Vec<Event>) -> Option<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.assigned(agent, job)?.events.extend(events);
+        Some(())
+    }
+
+    fn finish(&self, agent: &str, job: u64, finished: JobFinished) -> Option<()> {
+        let mut inner = self.inner.lock().unwrap();
+        let job = inner.assigned(agent, job)?;
+        job.finished = Some(finished.success);
+        job.exceeded = finished.exceeded;
+        Some(())
+    }
+
+    fn submit(&self, target: FuzzTarget) -> u64 {
+        let mut inner = self.inner.lock().unwrap();
+
+        inner.next_id += 1;
+        let id = inner.next_id;
+        inner.jobs.insert(
+            id,
+            JobState {
+                target: Some(target),
+                agent: None,
+                canceled: false,
+                stopped: false,
+                artifacts: Vec::new(),
+                events: Vec::new(),
+                finished: None,
+                exceeded: None,
+            },
+        );
+        inner.queue.push_back(id);
+
+        id
+    }
+
+    /// Checks the State of the Job and passes on its new Events, returns the Result once the Job
+    /// is done
+    fn poll(&self, id: u64, events: &mpsc::UnboundedSender<Event>) -> Option<Option<RunOutput>> {
+        let mut guard = self.inner.lock().unwrap();
+        let inner = &mut *guard;
+
+        // The Job is already gone, which happens if it was canceled
+        let job = match inner.jobs.get_mut(&id) {
+            Some(j) => j,
+            None => return Some(None),
+        };
+        for event in job.events.drain(..) {
+            let _ = events.send(event);
+        }
+
+        let done = match (job.finished, &job.agent) {
+            (Some(true), _) => Some(true),
+            (Some(false), _) => Some(false),
+            (None, Some(agent)) => match inner.agents.get(agent) {
+                Some(info) if now().saturating_sub(info.last_seen) < AGENT_TIMEOUT.as_secs() => {
+                    None
+                }
+                _ => {
+                    println!("Agent {} is gone", agent);
+                    Some(false)
+                }
+            },
+            (None, None) => None,
+        };
+
+        let success = done?;
+        let job = inner.jobs.remove(&id)?;
+        if success {
+            Some(Some(RunOutput {
+                artifacts: job.artifacts,
+                exceeded: job.exceeded,
+            }))
+        } else {
+            Some(None)
+        }
+    }
+
+    fn cancel(&self, id: u64) {
+        let mut inner = self.inner.lock().unwrap();
+
+        let assigned = match inner.jobs.get_mut(&id) {
+            Some(job) => {
+                job.canceled = true;
+                job.agent.is_some()
+            }
+            None => return,
+        };
+
+        // Jobs that were not picked up by an Agent yet can simply be dropped, the others are
+        // kept until the Agent received the cancel on its next Heartbeat
+        if !assigned {
+            inner.jobs.remove(&id);
+        }
+    }
+
+    /// Asks the Agent to stop the Job once the Budget is used up, the Job still finishes normally
+    fn stop(&self, id: u64) {
+        let mut inner = self.inner.lock().unwrap();
+
+        let assigned = match inner.jobs.get_mut(&id) {
+            Some(job) => {
+                job.stopped = true;
+                job.agent.is_some()
+            }
+            None => return,
+        };
+
+        // A Job that was never started has nothing to report
+        if !assigned {
+            inner.jobs.remove(&id);
+        }
+    }
+
+    /// Removes all the Agents that did not send a Heartbeat in time and the canceled Jobs that are
+    /// no longer running
+    fn prune(&self) {
+        let mut guard = self.inner.lock().unwrap();
+        let inner = &mut *guard;
+        let now = now();
+
+        inner
+            .agents
+            .retain(|_, a| now.saturating_sub(a.last_seen) < AGENT_TIMEOUT.as_secs());
+
+        // Canceled Jobs are only kept while their Agent still reports them as running
+        let agents = &inner.agents;
+        inner.jobs.retain(|id, j| {
+            !j.canceled
+                || j.agent
+                    .as_ref()
+                    .and_then(|a| agents.get(a))
+                    .map(|a| a.jobs.contains(id))
+                    .unwrap_or(false)
+        });
+    }
+}
+
+/// The HTTP-Routes used by the Agents to talk to the Coordinator
+pub fn routes(
+    coordinator: Arc<Coordinator>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let with_coordinator = warp::any().map(move || coordinator.clone());
+
+    let list = warp::path!("api" / "agents")
+        .and(warp::get())
+        .and(auth::identity())
+        .and(with_coordinator.clone())
+        .map(|identity: Identity, coordinator: Arc<Coordinator>| {
+            if !identity.admin {
+                return warp::reply::with_status(
+                    warp::reply::json(&"Forbidden"),
+                    StatusCode::FORBIDDEN,
+                );
+            }
+
+            warp::reply::with_status(warp::reply::json(&coordinator.agents()), StatusCode::OK)
+        });
+
+    let register = warp::path!("api" / "agents" / "register")
+        .and(warp::post())
+        .and(auth::authenticated())
+        .and(with_coordinator.clone())
+        .and(warp::body::json())
+        .map(|coordinator: Arc<Coordinator>, registration| {
+            warp::reply::json(&coordinator.register(registration))
+        });
+
+    let heartbeat = warp::path!("api" / "agents" / String / "heartbeat")
+        .and(warp::post())
+        .and(auth::authenticated())
+        .and(with_coordinator.clone())
+        .and(warp::body::json())
+        .map(|agent: String, coordinator: Arc<Coordinator>, heartbeat| {
+            coordinator.prune();
+
+            match coordinator.heartbeat(&agent, heartbeat) {
+                Some(r) => warp::reply::with_status(warp::reply::json(&r), StatusCode::OK),
+                None => unknown_agent(),
+            }
+        });
+
+    let next = warp::path!("api" / "agents" / String / "jobs" / "next")
+        .and(warp::post())
+        .and(auth::authenticated())
+        .and(with_coordinator.clone())
+        .map(
+            |agent: String, coordinator: Arc<Coordinator>| match coordinator.next_job(&agent) {
+                Some(job) => warp::reply::with_status(warp::reply::json(&job), StatusCode::OK),
+                None => unknown_agent(),
+            },
+        );
+
+    let log = warp::path!("api" / "agents" / String / "jobs" / u64 / "log")
+        .and(warp::post())
+        .and(auth::authenticated())
+        .and(with_coordinator.clone())
+        .and(warp::body::json())
+        .map(
+            |agent: String, job: u64, coordinator: Arc<Coordinator>, lines: Vec<String>| {
+                coordinator.log(&agent, job, lines);
+                StatusCode::OK
+            },
+        );
+
+    let artifact = warp::path!("api" / "agents" / String / "jobs" / u64 / "artifact")
+        .and(warp::post())
+        .and(auth::authenticated())
+        .and(with_coordinator.clone())
+        .and(warp::body::bytes())
+        .map(
+            |agent: String,
+             job: u64,
+             coordinator: Arc<Coordinator>,
+             body: warp::hyper::body::Bytes| {
+                match coordinator.artifact(&agent, job, body.to_vec()) {
+                    Some(_) => StatusCode::OK,
+                    None => StatusCode::NOT_FOUND,
+                }
+            },
+        );
+
+    let events = warp::path!("api" / "agents" / String / "jobs" / u64 / "events")
+        .and(warp::post())
+        .and(auth::authenticated())
+        .and(with_coordinator.clone())
+        .and(warp::body::json())
+        .map(
+            |agent: String, job: u64, coordinator: Arc<Coordinator>, events: Vec<Event>| {
+                match coordinator.events(&agent, job, events) {
+                    Some(_) => StatusCode::OK,
+                    None => StatusCode::NOT_FOUND,
+                }
+            },
+        );
+
+    let finish = warp::path!("api" / "agents" / String / "jobs" / u64 / "finish")
+        .and(warp::post())
+        .and(auth::authenticated())
+        .and(with_coordinator)
+        .and(warp::body::json())
+        .map(
+            |agent: String, job: u64, coordinator: Arc<Coordinator>, finished| match coordinator
+                .finish(&agent, job, finished)
+            {
+                Some(_) => StatusCode::OK,
+                None => StatusCode::NOT_FOUND,
+            },
+        );
+
+    list.or(register)
+        .or(heartbeat)
+        .or(next)
+        .or(log)
+        .or(artifact)
+        .or(events)
+        .or(finish)
+}
+
+fn unknown_agent() -> warp::reply::WithStatus<warp::reply::Json> {
+    warp::reply::with_status(warp::reply::json(&"Unknown Agent"), StatusCode::NOT_FOUND)
+}
+
+/// A Runner that hands all of its Targets to remote Agents
+pub struct RemoteRunner {
+    coordinator: Arc<Coordinator>,
+}
+
+impl RemoteRunner {
+    pub fn new(coordinator: Arc<Coordinator>) -> Self {
+        Self { coordinator }
+    }
+}
+
+#[async_trait]
+impl Runner for RemoteRunner {
+    async fn run(
+        &self,
+        target: FuzzTarget,
+        events: mpsc::UnboundedSender<Event>,
+        cancel: oneshot::Receiver<Stop>,
+    ) -> Option<RunOutput> {
+        let id = self.coordinator.submit(target);
+
+        let cancel = canceled(cancel);
+        tokio::pin!(cancel);
+        let mut stopped = false;
+
+        loop {
+            if let Some(result) = self.coordinator.poll(id, &events) {
+                return result;
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+                stop = &mut cancel, if !stopped => match stop {
+                    // The Agent still reports the Results of the Job once it stopped the Fuzzer
+                    Stop::Budget => {
+                        self.coordinator.stop(id);
+                        stopped = true;
+                    }
+                    Stop::Cancel => {
+                        self.coordinator.cancel(id);
+                        return None;
+                    }
+                },
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::project::{Source, Target};
+
+    use super::*;
+
+    fn register(coordinator: &Coordinator, name: &str) -> String {
+        coordinator
+            .register(Registration {
+                name: name.to_string(),
+            })
+            .id
+    }
+
+    fn finished() -> JobFinished {
+        JobFinished {
+            success: true,
+            exceeded: None,
+        }
+    }
+
+    fn submit(coordinator: &Coordinator) -> u64 {
+        let target: Target = serde_json::from_value(serde_json::json!({
+            "name": "target",
+            "folder": ".",
+            "target": { "CargoFuzz": { "name": "target" } },
+        }))
+        .unwrap();
+        let source = Source::Git {
+            repo: "repo".to_string(),
+        };
+        coordinator.submit(FuzzTarget::new("project", "target", target, source))
+    }
+
+    #[test]
+    fn only_assigned_agent_reports() {
+        let coordinator = Coordinator::new();
+        let first = register(&coordinator, "agent");
+        let second = register(&coordinator, "agent");
+        assert_ne!(first, second);
+        assert_eq!(64, first.len());
+
+        let id = submit(&coordinator);
+
+        // The Job is not assigned to any Agent yet
+        assert_eq!(None, coordinator.finish(&first, id, finished()));
+
+        let job = coordinator.next_job(&first).unwrap().unwrap();
+        assert_eq!(id, job.id);
+
+        assert_eq!(None, coordinator.artifact(&second, id, b"crash".to_vec()));
+        assert_eq!(None, coordinator.events(&second, id, vec![Event::Building]));
+        assert_eq!(None, coordinator.finish(&second, id, finished()));
+
+        assert_eq!(
+            Some(()),
+            coordinator.artifact(&first, id, b"crash".to_vec())
+        );
+        assert_eq!(Some(()), coordinator.finish(&first, id, finished()));
+    }
+
+    #[test]
+    fn stopped_job_reports_artifacts() {
+        let coordinator = Coordinator::new();
+        let agent = register(&coordinator, "agent");
+        let id = submit(&coordinator);
+        coordinator.next_job(&agent).unwrap().unwrap();
+
+        coordinator.stop(id);
+        let heartbeat = Heartbeat {
+            jobs: vec![id],
+            stats: serde_json::Value::Null,
+        };
+        let response = coordinator.heartbeat(&agent, heartbeat).unwrap();
+        assert_eq!(Vec::<u64>::new(), response.cancel);
+        assert_eq!(vec![id], response.stop);
+
+        let (events, _recv) = mpsc::unbounded_channel();
+        assert!(coordinator.poll(id, &events).is_none());
+
+        coordinator.artifact(&agent, id, b"crash".to_vec()).unwrap();
+        coordinator.finish(&agent, id, finished()).unwrap();
+        assert_eq!(
+            Some(Some(RunOutput {
+                artifacts: vec![b"crash".to_vec()],
+                exceeded: None,
+            })),
+            coordinator.poll(id, &events)
+        );
+    }
+
+    #[test]
+    fn canceled_job_pruned_once_stopped() {
+        let coordinator = Coordinator::new();
+        let agent = register(&coordinator, "agent");
+        let id = submit(&coordinator);
+        coordinator.next_job(&agent).unwrap().unwrap();
+
+        let heartbeat = |jobs: Vec<u64>| {
+            coordinator.prune();
+            coordinator
+                .heartbeat(
+                    &agent,
+                    Heartbeat {
+                        jobs,
+                        stats: serde_json::Value::Null,
+                    },
+                )
+                .unwrap()
+        };
+
+        heartbeat(vec![id]);
+        coordinator.cancel(id);
+
+        // The Job is kept until the Agent stopped running it
+        assert_eq!(vec![id], heartbeat(vec![id]).cancel);
+        assert!(coordinator.inner.lock().unwrap().jobs.contains_key(&id));
+
+        heartbeat(Vec::new());
+        heartbeat(Vec::new());
+        assert!(coordinator.inner.lock().unwrap().jobs.is_empty());
+    }
+}
@@ -0,0 +1,320 @@
+//! A Runner that executes everything inside of a Sandbox
+//!
+//! The Sandbox is created using [bubblewrap](https://github.com/containers/bubblewrap), which
+//! uses unprivileged user, mount, pid and network namespaces. Inside of the Sandbox the entire
+//! root Filesystem is mounted read-only and only the Workspace of the current Run is writable.
+//! The Network is only available while checking out and building the Target, the Fuzzer itself
+//! runs without any Network access.
+//!
+//! Secrets of the Server, like its Configuration, Database and Credentials, are hidden from the
+//! Sandbox and only an allowlist of Environment-Variables is passed into it.
+
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use async_trait::async_trait;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::FuzzTarget;
+
+use super::{
+    process::{Launcher, Phase, ProcessRunner},
+    Event, RunOutput, Runner, Stop,
+};
+
+/// The Environment-Variables of the Server that are passed into the Sandbox
+const ENV_ALLOWLIST: &[&str] = &["PATH", "HOME", "CARGO_HOME", "RUSTUP_HOME"];
+
+/// A Runner that works like the [`ProcessRunner`] but executes every Command inside of a Sandbox
+pub struct SandboxRunner {
+    inner: ProcessRunner,
+}
+
+impl SandboxRunner {
+    /// Creates a new SandboxRunner using the default Bubblewrap configuration
+    pub fn new<P>(path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self::with_sandbox(path, Bubblewrap::default())
+    }
+
+    /// Creates a new SandboxRunner using the given Bubblewrap configuration
+    pub fn with_sandbox<P>(path: P, sandbox: Bubblewrap) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            inner: ProcessRunner::with_launcher(path, sandbox),
+        }
+    }
+
+    /// See [`ProcessRunner::with_cgroup`]
+    pub fn with_cgroup<P>(mut self, root: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.inner = self.inner.with_cgroup(root);
+        self
+    }
+}
+
+#[async_trait]
+impl Runner for SandboxRunner {
+    async fn run(
+        &self,
+        target: FuzzTarget,
+        events: mpsc::UnboundedSender<Event>,
+        cancel: oneshot::Receiver<Stop>,
+    ) -> Option<RunOutput> {
+        Runner::run(&self.inner, target, events, cancel).await
+    }
+}
+
+/// The Configuration for the Bubblewrap Sandbox
+#[derive(Debug, Clone)]
+pub struct Bubblewrap {
+    /// The Path to the `bwrap` Binary
+    pub program: PathBuf,
+    /// Additional Paths that are writable while building, like the Cargo Home for downloading
+    /// the Dependencies
+    pub build_paths: Vec<PathBuf>,
+    /// Paths that are hidden inside of the Sandbox, like the Configuration and the Database
+    pub hidden_paths: Vec<PathBuf>,
+}
+
+impl Default for Bubblewrap {
+    fn default() -> Self {
+        let cargo_home = std::env::var_os("CARGO_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cargo")));
+
+        Self {
+            program: PathBuf::from("bwrap"),
+            build_paths: cargo_home.into_iter().collect(),
+            hidden_paths: Vec::new(),
+        }
+    }
+}
+
+impl Bubblewrap {
+    /// The Arguments for `bwrap` that set up the Sandbox for the Phase, in which only the
+    /// writable Paths can be modified
+    fn arguments(&self, phase: Phase, writable: &[&Path]) -> std::io::Result<Vec<OsString>> {
+        let mut args: Vec<OsString> = [
+            "--ro-bind",
+            "/",
+            "/",
+            "--dev",
+            "/dev",
+            "--proc",
+            "/proc",
+            "--tmpfs",
+            "/tmp",
+            "--unshare-user",
+            "--unshare-pid",
+            "--unshare-ipc",
+            "--unshare-uts",
+            "--die-with-parent",
+        ]
+        .iter()
+        .map(OsString::from)
+        .collect();
+
+        if phase == Phase::Fuzz {
+            args.push("--unshare-net".into());
+        }
+
+        let build_paths = self.build_paths.iter().map(|p| p.as_path());
+        let extra: Vec<&Path> = match phase {
+            Phase::Build => build_paths.collect(),
+            _ => Vec::new(),
+        };
+        for path in writable.iter().chain(extra.iter()) {
+            // bwrap needs absolute Paths, which only exist for already created Folders
+            std::fs::create_dir_all(path)?;
+            let path = std::fs::canonicalize(path)?;
+
+            args.push("--bind".into());
+            args.push(path.clone().into());
+            args.push(path.into());
+        }
+
+        // The hidden Paths are mounted last, so they are also hidden inside of writable Paths
+        for path in self.hidden_paths.iter() {
+            let path = match std::fs::canonicalize(path) {
+                Ok(p) => p,
+                // There is nothing to hide
+                Err(_) => continue,
+            };
+
+            if path.is_dir() {
+                args.push("--tmpfs".into());
+            } else {
+                args.push("--ro-bind".into());
+                args.push("/dev/null".into());
+            }
+            args.push(path.into());
+        }
+
+        Ok(args)
+    }
+}
+
+impl Launcher for Bubblewrap {
+    fn launch(&self, phase: Phase, writable: &[&Path], cmd: Command) -> std::io::Result<Command> {
+        let mut sandboxed = Command::new(&self.program);
+        sandboxed.args(self.arguments(phase, writable)?);
+
+        // bwrap keeps the working directory and the Environment, so they can simply be moved to
+        // the outer Command
+        if let Some(dir) = cmd.get_current_dir() {
+            sandboxed.current_dir(dir);
+        }
+        sandboxed.env_clear();
+        for key in ENV_ALLOWLIST {
+            if let Some(value) = std::env::var_os(key) {
+                sandboxed.env(key, value);
+            }
+        }
+        for (key, value) in cmd.get_envs() {
+            match value {
+                Some(v) => sandboxed.env(key, v),
+                None => sandboxed.env_remove(key),
+            };
+        }
+
+        sandboxed.arg("--").arg(cmd.get_program()).args(cmd.get_args());
+
+        Ok(sandboxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The Paths that are bind-mounted writable by the Arguments
+    fn binds(args: &[OsString]) -> Vec<PathBuf> {
+        args.windows(3)
+            .filter(|w| w[0] == "--bind")
+            .map(|w| PathBuf::from(&w[1]))
+            .collect()
+    }
+
+    #[test]
+    fn network_only_unshared_while_fuzzing() {
+        let sandbox = Bubblewrap::default();
+
+        for (phase, unshared) in [
+            (Phase::Checkout, false),
+            (Phase::Build, false),
+            (Phase::Fuzz, true),
+        ] {
+            let args = sandbox.arguments(phase, &[]).unwrap();
+            assert_eq!(
+                unshared,
+                args.contains(&OsString::from("--unshare-net")),
+                "{:?}",
+                phase
+            );
+            assert!(args.contains(&OsString::from("--unshare-user")));
+        }
+    }
+
+    #[test]
+    fn build_paths_only_writable_while_building() {
+        let workspace = tempfile::tempdir().unwrap();
+        let cargo_home = tempfile::tempdir().unwrap();
+        let sandbox = Bubblewrap {
+            program: PathBuf::from("bwrap"),
+            build_paths: vec![cargo_home.path().to_path_buf()],
+            hidden_paths: Vec::new(),
+        };
+
+        let workspace_path = std::fs::canonicalize(workspace.path()).unwrap();
+        let cargo_path = std::fs::canonicalize(cargo_home.path()).unwrap();
+        let writable = [workspace.path()];
+
+        assert_eq!(
+            vec![workspace_path.clone(), cargo_path],
+            binds(&sandbox.arguments(Phase::Build, &writable).unwrap())
+        );
+        assert_eq!(
+            vec![workspace_path.clone()],
+            binds(&sandbox.arguments(Phase::Fuzz, &writable).unwrap())
+        );
+        assert_eq!(
+            vec![workspace_path],
+            binds(&sandbox.arguments(Phase::Checkout, &writable).unwrap())
+        );
+    }
+
+    #[test]
+    fn missing_paths_are_created() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing");
+        let sandbox = Bubblewrap::default();
+
+        let args = sandbox.arguments(Phase::Fuzz, &[&missing]).unwrap();
+        assert!(missing.is_dir());
+        assert_eq!(vec![std::fs::canonicalize(&missing).unwrap()], binds(&args));
+
+        let file = dir.path().join("file");
+        std::fs::write(&file, "").unwrap();
+        let nested = file.join("sub");
+        assert!(sandbox.arguments(Phase::Fuzz, &[&nested]).is_err());
+    }
+
+    #[test]
+    fn secrets_are_hidden() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("cfuzz.toml");
+        std::fs::write(&config, "").unwrap();
+        let blobs = dir.path().join("blobs");
+        std::fs::create_dir(&blobs).unwrap();
+
+        let sandbox = Bubblewrap {
+            hidden_paths: vec![config.clone(), blobs.clone(), dir.path().join("missing")],
+            ..Bubblewrap::default()
+        };
+        let args = sandbox.arguments(Phase::Fuzz, &[dir.path()]).unwrap();
+
+        let config = std::fs::canonicalize(config).unwrap().into_os_string();
+        let blobs = std::fs::canonicalize(blobs).unwrap().into_os_string();
+        let end = &args[args.len() - 5..];
+        assert_eq!(["--ro-bind".into(), "/dev/null".into(), config], end[..3]);
+        assert_eq!(["--tmpfs".into(), blobs], end[3..]);
+    }
+
+    #[test]
+    fn only_allowed_env_passed() {
+        // The fake bwrap simply prints the Environment it received
+        let dir = tempfile::tempdir().unwrap();
+        let bwrap = dir.path().join("bwrap");
+        std::fs::write(&bwrap, "#!/bin/sh\nexec env\n").unwrap();
+        std::fs::set_permissions(&bwrap, std::os::unix::fs::PermissionsExt::from_mode(0o755))
+            .unwrap();
+        std::env::set_var("CFUZZ_SANDBOX_SECRET", "secret");
+
+        let sandbox = Bubblewrap {
+            program: bwrap,
+            ..Bubblewrap::default()
+        };
+        let mut cmd = Command::new("true");
+        cmd.env("CFUZZ_CORPUS", "/corpus");
+        let output = sandbox
+            .launch(Phase::Fuzz, &[], cmd)
+            .unwrap()
+            .output()
+            .unwrap();
+        let env = String::from_utf8(output.stdout).unwrap();
+
+        assert!(env.lines().any(|l| l == "CFUZZ_CORPUS=/corpus"));
+        assert!(env.lines().any(|l| l.starts_with("PATH=")));
+        assert!(!env.contains("CFUZZ_SANDBOX_SECRET"));
+    }
+}
@@ -0,0 +1,143 @@
+//! Keeps track of the recent Runs and their Outcome
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    project::Sanitizer,
+    runner::{Event, Limit, RunOutput, Stats},
+};
+
+/// The Number of Runs that are kept, older finished Runs are dropped
+const MAX_RUNS: usize = 1000;
+
+/// The Status of a Run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RunStatus {
+    Running,
+    Finished,
+    Failed,
+}
+
+/// The Record of a single Run of a Target
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub id: u64,
+    pub project: String,
+    pub target: String,
+    pub sanitizer: Option<Sanitizer>,
+    /// The Start of the Run in Seconds since the Unix-Epoch
+    pub started: u64,
+    /// The End of the Run in Seconds since the Unix-Epoch
+    pub finished: Option<u64>,
+    pub status: RunStatus,
+    /// The Number of Crashes found by the Run
+    pub crashes: usize,
+    /// The Resource-Limit of the Target that was exceeded by the Run
+    pub exceeded: Option<Limit>,
+    /// All the Events of the Run, except for the Statistics
+    pub events: Vec<Event>,
+    /// The latest Statistics of every Worker
+    pub stats: Vec<Stats>,
+}
+
+/// An Event of a specific Run, as it is send to the Subscribers
+#[derive(Debug, Clone, Serialize)]
+pub struct RunEvent {
+    pub run: u64,
+    pub project: String,
+    pub target: String,
+    pub sanitizer: Option<Sanitizer>,
+    pub event: Event,
+}
+
+/// The Records of the most recent Runs
+#[derive(Debug, Default)]
+pub struct Runs {
+    next_id: u64,
+    records: VecDeque<RunRecord>,
+}
+
+impl Runs {
+    /// Records the Start of a new Run and returns its ID
+    pub fn start(&mut self, project: &str, target: &str, sanitizer: Option<Sanitizer>) -> u64 {
+        self.next_id += 1;
+        let id = self.next_id;
+
+        self.records.push_back(RunRecord {
+            id,
+            project: project.to_string(),
+            target: target.to_string(),
+            sanitizer,
+            started: crate::now(),
+            finished: None,
+            status: RunStatus::Running,
+            crashes: 0,
+            exceeded: None,
+            events: Vec::new(),
+            stats: Vec::new(),
+        });
+
+        while self.records.len() > MAX_RUNS {
+            match self
+                .records
+                .iter()
+                .position(|r| r.status != RunStatus::Running)
+            {
+                Some(index) => self.records.remove(index),
+                None => break,
+            };
+        }
+
+        id
+    }
+
+    /// Records the Event for the Run and returns it, so that it can be send to the Subscribers
+    pub fn event(&mut self, id: u64, event: Event) -> Option<RunEvent> {
+        let record = self.records.iter_mut().find(|r| r.id == id)?;
+
+        match &event {
+            Event::Stats(stats) => {
+                match record.stats.iter_mut().find(|s| s.worker == stats.worker) {
+                    Some(s) => *s = stats.clone(),
+                    None => record.stats.push(stats.clone()),
+                };
+            }
+            _ => record.events.push(event.clone()),
+        };
+
+        Some(RunEvent {
+            run: id,
+            project: record.project.clone(),
+            target: record.target.clone(),
+            sanitizer: record.sanitizer,
+            event,
+        })
+    }
+
+    /// Records the Outcome of the Run, a missing Output means the Run failed
+    pub fn finish(&mut self, id: u64, output: Option<&RunOutput>) {
+        let record = match self.records.iter_mut().find(|r| r.id == id) {
+            Some(r) => r,
+            None => return,
+        };
+
+        record.finished = Some(crate::now());
+        match output {
+            Some(output) => {
+                record.status = RunStatus::Finished;
+                record.crashes = output.artifacts.len();
+                record.exceeded = output.exceeded;
+            }
+            None => {
+                record.status = RunStatus::Failed;
+            }
+        };
+    }
+
+    /// All the known Runs, from oldest to newest
+    pub fn records(&self) -> Vec<RunRecord> {
+        self.records.iter().cloned().collect()
+    }
+}
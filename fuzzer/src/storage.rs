@@ -1,12 +1,20 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
 
 use tokio::sync::{mpsc, oneshot};
+use warp::{http::StatusCode, reject::Reject};
 
 use crate::{
+    auth::{ApiToken, Member, Session, User},
     project::{Project, Target},
+    updates::{Update, Updates},
     FuzzResult,
 };
 
+use self::blobs::BlobStore;
+
+pub mod blobs;
+pub mod memory;
+pub mod postgres;
 pub mod sqlite;
 
 /// A Storage-Backend that can be used to store all the Data generated and configured by the Program.
@@ -20,7 +28,69 @@ pub trait StorageBackend {
     ///
     /// This should not block and instead perform only the setup that needs to be done before being able to potentially
     /// serve requests and then move all the long running tasks (like handling the requests) to a background task/thread
-    fn run(self, recv: mpsc::Receiver<(StorageRequest, oneshot::Sender<StorageResult>)>);
+    ///
+    /// A Request that can not be performed is answered with an Error, instead of stopping the Backend
+    fn run(
+        self,
+        recv: mpsc::Receiver<(
+            StorageRequest,
+            oneshot::Sender<Result<StorageResult, StorageError>>,
+        )>,
+    );
+}
+
+/// The Reasons for a Storage Request to fail
+#[derive(Debug)]
+pub enum StorageError {
+    /// The Backend is not running anymore
+    Unavailable,
+    /// The Backend failed to perform the Request, like a Query that could not be executed
+    Backend(String),
+    /// The stored Data could not be decoded
+    Corrupt(String),
+    /// The Backend answered with the Result for a different Request
+    Unexpected,
+}
+
+impl std::fmt::Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unavailable => write!(f, "The Storage is unavailable"),
+            Self::Backend(e) => write!(f, "Storage: {}", e),
+            Self::Corrupt(e) => write!(f, "Corrupt Data in the Storage: {}", e),
+            Self::Unexpected => write!(f, "The Storage returned an unexpected Result"),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl Reject for StorageError {}
+
+impl StorageError {
+    /// The Response to an HTTP-Request that failed because of this Error
+    pub fn reply(&self) -> warp::reply::WithStatus<String> {
+        eprintln!("{}", self);
+
+        let status = match self {
+            Self::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        warp::reply::with_status(self.to_string(), status)
+    }
+}
+
+impl From<serde_json::Error> for StorageError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Corrupt(e.to_string())
+    }
+}
+
+/// Allows using `?` for Storage Requests in Functions that return their Errors as a String
+impl From<StorageError> for String {
+    fn from(e: StorageError) -> Self {
+        e.to_string()
+    }
 }
 
 /// A Request for the Storage Backend
@@ -51,7 +121,12 @@ pub enum StorageRequest {
     /// Add a new Target to a Project
     AddProjectTarget {
         project_name: String,
-        target: Target,
+        target: Box<Target>,
+    },
+    /// Replace the existing Target with the same Name in the Project, without creating a new one
+    UpdateProjectTarget {
+        project_name: String,
+        target: Box<Target>,
     },
     /// Should attempt to load the Target with the given Name from the Project
     LoadTarget {
@@ -67,6 +142,34 @@ pub enum StorageRequest {
         /// The Name of the Target
         target_name: String,
     },
+    /// Should store/update the User
+    StoreUser(User),
+    /// Should attempt to load the User with the given Name
+    LoadUser { name: String },
+    /// Should load all Users
+    LoadUsers,
+    /// Should store the new Session
+    StoreSession(Session),
+    /// Should attempt to load the Session with the given Token-Hash
+    LoadSession { token_hash: String },
+    /// Should remove the Session with the given Token-Hash
+    RemoveSession { token_hash: String },
+    /// Should store/update the API-Token
+    StoreToken(ApiToken),
+    /// Should attempt to load the API-Token with the given Hash
+    LoadToken { token_hash: String },
+    /// Should load all the API-Tokens of the User
+    LoadTokens { user: String },
+    /// Should remove the API-Token of the User with the given Name
+    RemoveToken { user: String, name: String },
+    /// Should store/update the Membership of the User in the Project
+    StoreMember(Member),
+    /// Should remove the User from the Project
+    RemoveMember { project: String, user: String },
+    /// Should load all the Members of the Project
+    LoadMembers { project: String },
+    /// Should load all the Memberships of the User
+    LoadMemberships { user: String },
 }
 
 /// A Result returned by the Storage Backend for a Request
@@ -78,14 +181,37 @@ pub enum StorageResult {
     LoadProjects(Vec<Project>),
     LoadProject(Option<Project>),
     AddProjectTarget,
-    LoadTarget(Option<Target>),
+    /// If there was a Target to update
+    UpdateProjectTarget(bool),
+    LoadTarget(Option<Box<Target>>),
     RemoveTarget,
+    StoreUser,
+    LoadUser(Option<User>),
+    LoadUsers(Vec<User>),
+    StoreSession,
+    LoadSession(Option<Session>),
+    RemoveSession,
+    StoreToken,
+    LoadToken(Option<ApiToken>),
+    LoadTokens(Vec<ApiToken>),
+    RemoveToken,
+    StoreMember,
+    RemoveMember,
+    LoadMembers(Vec<Member>),
+    LoadMemberships(Vec<Member>),
 }
 
 /// The Handle allows for easy interaction with a Storage Backend
 pub struct StorageHandle {
     /// The Queue used for communicating with the Backend
-    coms: mpsc::Sender<(StorageRequest, oneshot::Sender<StorageResult>)>,
+    coms: mpsc::Sender<(
+        StorageRequest,
+        oneshot::Sender<Result<StorageResult, StorageError>>,
+    )>,
+    /// Receives an Update for every Change to the stored Data
+    updates: Option<Updates>,
+    /// Stores the Inputs of the Results, instead of storing them in the Backend itself
+    blobs: Option<Arc<dyn BlobStore + Send + Sync>>,
 }
 
 impl Debug for StorageHandle {
@@ -98,109 +224,410 @@ pub fn start<S>(backend: S) -> StorageHandle
 where
     S: StorageBackend,
 {
-    let (coms, recv) = mpsc::channel::<(StorageRequest, oneshot::Sender<StorageResult>)>(64);
+    let (coms, recv) = mpsc::channel(64);
 
     backend.run(recv);
 
-    StorageHandle { coms }
+    StorageHandle {
+        coms,
+        updates: None,
+        blobs: None,
+    }
 }
 
 impl StorageHandle {
-    async fn request(&self, req: StorageRequest) -> Option<StorageResult> {
+    /// Sends an Update for every Change made through this Handle
+    pub fn with_updates(mut self, updates: Updates) -> Self {
+        self.updates = Some(updates);
+        self
+    }
+
+    /// Stores the Inputs of new Results in the Blob-Store, only their Hashes are stored in the Backend
+    pub fn with_blobs(mut self, blobs: Arc<dyn BlobStore + Send + Sync>) -> Self {
+        self.blobs = Some(blobs);
+        self
+    }
+
+    fn update(&self, update: Update) {
+        if let Some(updates) = &self.updates {
+            updates.send(update);
+        }
+    }
+
+    async fn request(&self, req: StorageRequest) -> Result<StorageResult, StorageError> {
         let (send, recv) = oneshot::channel();
 
-        self.coms.send((req, send)).await.ok()?;
+        self.coms
+            .send((req, send))
+            .await
+            .map_err(|_| StorageError::Unavailable)?;
 
-        recv.await.ok()
+        recv.await.map_err(|_| StorageError::Unavailable)?
     }
 
-    pub async fn store_result(&self, project: String, data: FuzzResult) {
-        self.request(StorageRequest::StoreResult {
-            project_name: project,
-            result: data,
-        })
-        .await
-        .unwrap();
+    pub async fn store_result(
+        &self,
+        project: String,
+        mut data: FuzzResult,
+    ) -> Result<(), StorageError> {
+        if let Some(blobs) = &self.blobs {
+            let hash = blobs::hash(&data.content);
+            match blobs.put(&hash, &data.content).await {
+                Ok(_) => {
+                    data.content = Vec::new();
+                    data.blob = Some(hash);
+                }
+                Err(e) => {
+                    eprintln!("Storing Result in Blob-Store: {}", e);
+                }
+            };
+        }
+
+        let update = Update::ResultStored {
+            project: project.clone(),
+            target: data.name.clone(),
+            sanitizer: data.sanitizer,
+        };
+
+        let stored = self
+            .request(StorageRequest::StoreResult {
+                project_name: project,
+                result: data,
+            })
+            .await?;
+        match stored {
+            StorageResult::Store => {}
+            _ => return Err(StorageError::Unexpected),
+        }
+
+        self.update(update);
+
+        Ok(())
     }
 
-    pub async fn load_results(&self, project: String) -> Vec<FuzzResult> {
+    pub async fn load_results(&self, project: String) -> Result<Vec<FuzzResult>, StorageError> {
         match self
             .request(StorageRequest::LoadResults { project })
-            .await
-            .unwrap()
+            .await?
         {
-            StorageResult::LoadResults(r) => r,
-            _ => unreachable!(),
+            StorageResult::LoadResults(r) => Ok(self.load_blobs(r).await),
+            _ => Err(StorageError::Unexpected),
         }
     }
 
-    pub async fn update_project(&self, project: Project) {
-        match self
-            .request(StorageRequest::StoreProject(project))
-            .await
-            .unwrap()
-        {
+    /// Loads the Content of the Results that are stored in the Blob-Store
+    async fn load_blobs(&self, results: Vec<FuzzResult>) -> Vec<FuzzResult> {
+        let mut loaded = Vec::with_capacity(results.len());
+        for mut result in results {
+            let hash = match &result.blob {
+                Some(h) => h.clone(),
+                None => {
+                    loaded.push(result);
+                    continue;
+                }
+            };
+
+            let blobs = match &self.blobs {
+                Some(b) => b,
+                None => {
+                    eprintln!(
+                        "Result {} is stored in a Blob-Store, but none is configured",
+                        hash
+                    );
+                    continue;
+                }
+            };
+
+            match blobs.get(&hash).await {
+                Ok(Some(content)) => {
+                    result.content = content;
+                    loaded.push(result);
+                }
+                Ok(None) => {
+                    eprintln!("Blob {} for Result is missing", hash);
+                }
+                Err(e) => {
+                    eprintln!("Loading Result from Blob-Store: {}", e);
+                }
+            };
+        }
+
+        loaded
+    }
+
+    pub async fn update_project(&self, project: Project) -> Result<(), StorageError> {
+        let update = Update::ProjectChanged {
+            project: project.name.clone(),
+        };
+
+        match self.request(StorageRequest::StoreProject(project)).await? {
             StorageResult::StoreProject => {}
-            _ => unreachable!(),
+            _ => return Err(StorageError::Unexpected),
         };
+
+        self.update(update);
+
+        Ok(())
     }
 
-    pub async fn remove_project(&self, name: String) {
+    pub async fn remove_project(&self, name: String) -> Result<(), StorageError> {
         match self
-            .request(StorageRequest::RemoveProject { name })
-            .await
-            .unwrap()
+            .request(StorageRequest::RemoveProject { name: name.clone() })
+            .await?
         {
             StorageResult::RemoveProject => {}
-            _ => unreachable!(),
+            _ => return Err(StorageError::Unexpected),
         };
+
+        self.update(Update::ProjectRemoved { project: name });
+
+        Ok(())
     }
 
-    pub async fn load_projects(&self) -> Vec<Project> {
-        match self.request(StorageRequest::LoadProjects).await.unwrap() {
-            StorageResult::LoadProjects(r) => r,
-            _ => unreachable!(),
+    pub async fn load_projects(&self) -> Result<Vec<Project>, StorageError> {
+        match self.request(StorageRequest::LoadProjects).await? {
+            StorageResult::LoadProjects(r) => Ok(r),
+            _ => Err(StorageError::Unexpected),
         }
     }
 
-    pub async fn load_project<N>(&self, name: N) -> Option<Project>
+    pub async fn load_project<N>(&self, name: N) -> Result<Option<Project>, StorageError>
     where
         N: Into<String>,
     {
         match self
             .request(StorageRequest::LoadProject { name: name.into() })
-            .await
-            .unwrap()
+            .await?
         {
-            StorageResult::LoadProject(d) => d,
-            _ => unreachable!(),
+            StorageResult::LoadProject(d) => Ok(d),
+            _ => Err(StorageError::Unexpected),
         }
     }
 
-    pub async fn add_project_target(&self, pname: String, target: Target) {
+    pub async fn load_target(
+        &self,
+        pname: String,
+        target: String,
+    ) -> Result<Option<Target>, StorageError> {
         match self
-            .request(StorageRequest::AddProjectTarget {
+            .request(StorageRequest::LoadTarget {
                 project_name: pname,
-                target,
+                target_name: target,
             })
-            .await
-            .unwrap()
+            .await?
+        {
+            StorageResult::LoadTarget(t) => Ok(t.map(|t| *t)),
+            _ => Err(StorageError::Unexpected),
+        }
+    }
+
+    pub async fn add_project_target(
+        &self,
+        pname: String,
+        target: Target,
+    ) -> Result<(), StorageError> {
+        match self
+            .request(StorageRequest::AddProjectTarget {
+                project_name: pname.clone(),
+                target: Box::new(target),
+            })
+            .await?
         {
             StorageResult::AddProjectTarget => {}
-            _ => unreachable!(),
+            _ => return Err(StorageError::Unexpected),
+        }
+
+        self.update(Update::ProjectChanged { project: pname });
+
+        Ok(())
+    }
+
+    /// Replaces the existing Target, returns false if the Project has no Target with that Name
+    pub async fn update_project_target(
+        &self,
+        pname: String,
+        target: Target,
+    ) -> Result<bool, StorageError> {
+        let updated = match self
+            .request(StorageRequest::UpdateProjectTarget {
+                project_name: pname.clone(),
+                target: Box::new(target),
+            })
+            .await?
+        {
+            StorageResult::UpdateProjectTarget(u) => u,
+            _ => return Err(StorageError::Unexpected),
+        };
+
+        if updated {
+            self.update(Update::ProjectChanged { project: pname });
         }
+
+        Ok(updated)
     }
-    pub async fn remove_project_target(&self, pname: String, target: String) {
+
+    pub async fn remove_project_target(
+        &self,
+        pname: String,
+        target: String,
+    ) -> Result<(), StorageError> {
         match self
             .request(StorageRequest::RemoveTarget {
-                project_name: pname,
+                project_name: pname.clone(),
                 target_name: target,
             })
-            .await
-            .unwrap()
+            .await?
         {
             StorageResult::RemoveTarget => {}
-            _ => unreachable!(),
+            _ => return Err(StorageError::Unexpected),
+        }
+
+        self.update(Update::ProjectChanged { project: pname });
+
+        Ok(())
+    }
+
+    pub async fn store_user(&self, user: User) -> Result<(), StorageError> {
+        match self.request(StorageRequest::StoreUser(user)).await? {
+            StorageResult::StoreUser => {}
+            _ => return Err(StorageError::Unexpected),
+        }
+
+        Ok(())
+    }
+
+    pub async fn load_user(&self, name: String) -> Result<Option<User>, StorageError> {
+        match self.request(StorageRequest::LoadUser { name }).await? {
+            StorageResult::LoadUser(u) => Ok(u),
+            _ => Err(StorageError::Unexpected),
+        }
+    }
+
+    pub async fn load_users(&self) -> Result<Vec<User>, StorageError> {
+        match self.request(StorageRequest::LoadUsers).await? {
+            StorageResult::LoadUsers(u) => Ok(u),
+            _ => Err(StorageError::Unexpected),
+        }
+    }
+
+    pub async fn store_session(&self, session: Session) -> Result<(), StorageError> {
+        match self.request(StorageRequest::StoreSession(session)).await? {
+            StorageResult::StoreSession => {}
+            _ => return Err(StorageError::Unexpected),
+        }
+
+        Ok(())
+    }
+
+    pub async fn load_session(&self, token_hash: String) -> Result<Option<Session>, StorageError> {
+        match self
+            .request(StorageRequest::LoadSession { token_hash })
+            .await?
+        {
+            StorageResult::LoadSession(s) => Ok(s),
+            _ => Err(StorageError::Unexpected),
+        }
+    }
+
+    pub async fn remove_session(&self, token_hash: String) -> Result<(), StorageError> {
+        match self
+            .request(StorageRequest::RemoveSession { token_hash })
+            .await?
+        {
+            StorageResult::RemoveSession => {}
+            _ => return Err(StorageError::Unexpected),
+        }
+
+        Ok(())
+    }
+
+    pub async fn store_token(&self, token: ApiToken) -> Result<(), StorageError> {
+        match self.request(StorageRequest::StoreToken(token)).await? {
+            StorageResult::StoreToken => {}
+            _ => return Err(StorageError::Unexpected),
+        }
+
+        Ok(())
+    }
+
+    pub async fn load_token(&self, token_hash: String) -> Result<Option<ApiToken>, StorageError> {
+        match self
+            .request(StorageRequest::LoadToken { token_hash })
+            .await?
+        {
+            StorageResult::LoadToken(t) => Ok(t),
+            _ => Err(StorageError::Unexpected),
+        }
+    }
+
+    pub async fn load_tokens(&self, user: String) -> Result<Vec<ApiToken>, StorageError> {
+        match self.request(StorageRequest::LoadTokens { user }).await? {
+            StorageResult::LoadTokens(t) => Ok(t),
+            _ => Err(StorageError::Unexpected),
+        }
+    }
+
+    pub async fn remove_token(&self, user: String, name: String) -> Result<(), StorageError> {
+        match self
+            .request(StorageRequest::RemoveToken { user, name })
+            .await?
+        {
+            StorageResult::RemoveToken => {}
+            _ => return Err(StorageError::Unexpected),
+        }
+
+        Ok(())
+    }
+
+    pub async fn store_member(&self, member: Member) -> Result<(), StorageError> {
+        let update = Update::ProjectChanged {
+            project: member.project.clone(),
+        };
+
+        match self.request(StorageRequest::StoreMember(member)).await? {
+            StorageResult::StoreMember => {}
+            _ => return Err(StorageError::Unexpected),
+        }
+
+        self.update(update);
+
+        Ok(())
+    }
+
+    pub async fn remove_member(&self, project: String, user: String) -> Result<(), StorageError> {
+        match self
+            .request(StorageRequest::RemoveMember {
+                project: project.clone(),
+                user,
+            })
+            .await?
+        {
+            StorageResult::RemoveMember => {}
+            _ => return Err(StorageError::Unexpected),
+        }
+
+        self.update(Update::ProjectChanged { project });
+
+        Ok(())
+    }
+
+    pub async fn load_members(&self, project: String) -> Result<Vec<Member>, StorageError> {
+        match self
+            .request(StorageRequest::LoadMembers { project })
+            .await?
+        {
+            StorageResult::LoadMembers(m) => Ok(m),
+            _ => Err(StorageError::Unexpected),
+        }
+    }
+
+    pub async fn load_memberships(&self, user: String) -> Result<Vec<Member>, StorageError> {
+        match self
+            .request(StorageRequest::LoadMemberships { user })
+            .await?
+        {
+            StorageResult::LoadMemberships(m) => Ok(m),
+            _ => Err(StorageError::Unexpected),
         }
     }
 }
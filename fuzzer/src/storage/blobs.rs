@@ -0,0 +1,116 @@
+//! Content-addressed Storage for large Data, like the Inputs of the Results
+//!
+//! Storing the Inputs outside of the Database keeps the Database small, as it only needs to store
+//! the Hashes of the Inputs. The Blobs are named after the SHA-256 Hash of their Content, so the
+//! same Input is only stored once. Blobs are never deleted, as they might still be referenced by
+//! other Results.
+
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+
+pub mod s3;
+
+/// A Store for Blobs, which are identified by the Hash of their Content
+#[async_trait]
+pub trait BlobStore {
+    /// Stores the Content under its Hash, storing a Blob that already exists does nothing
+    async fn put(&self, hash: &str, content: &[u8]) -> Result<(), String>;
+
+    /// Loads the Content of the Blob, if it exists
+    async fn get(&self, hash: &str) -> Result<Option<Vec<u8>>, String>;
+}
+
+/// The hex-encoded SHA-256 Hash of the Content, which is used as the Name of its Blob
+pub fn hash(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Makes sure that concurrent Puts of the same Blob use different temporary Files
+static TEMP_FILES: AtomicU64 = AtomicU64::new(0);
+
+/// Makes sure that the Hash can safely be used as a Path or Key
+fn check_hash(hash: &str) -> Result<(), String> {
+    if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("Invalid Blob {:?}", hash));
+    }
+
+    Ok(())
+}
+
+/// Stores the Blobs as Files in a Folder, where the Blobs are spread over Subfolders named after
+/// the first two Characters of their Hash
+pub struct FsBlobStore {
+    root: PathBuf,
+}
+
+impl FsBlobStore {
+    pub fn new<P>(root: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, hash: &str) -> PathBuf {
+        self.root.join(&hash[..2]).join(hash)
+    }
+}
+
+#[async_trait]
+impl BlobStore for FsBlobStore {
+    async fn put(&self, hash: &str, content: &[u8]) -> Result<(), String> {
+        check_hash(hash)?;
+
+        let path = self.path(hash);
+        if tokio::fs::metadata(&path).await.is_ok() {
+            return Ok(());
+        }
+
+        let folder = path.parent().unwrap();
+        tokio::fs::create_dir_all(folder)
+            .await
+            .map_err(|e| format!("Creating {}: {}", folder.display(), e))?;
+
+        // Writing to a temporary File first makes sure that no partial Blob is ever visible
+        let tmp = folder.join(format!(
+            ".{}.{}.{}",
+            hash,
+            std::process::id(),
+            TEMP_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let written = match tokio::fs::write(&tmp, content).await {
+            Ok(_) => tokio::fs::rename(&tmp, &path).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            let _ = tokio::fs::remove_file(&tmp).await;
+
+            // Someone else stored the same Blob in the meantime
+            if tokio::fs::metadata(&path).await.is_ok() {
+                return Ok(());
+            }
+            return Err(format!("Writing {}: {}", path.display(), e));
+        }
+
+        Ok(())
+    }
+
+    async fn get(&self, hash: &str) -> Result<Option<Vec<u8>>, String> {
+        check_hash(hash)?;
+
+        let path = self.path(hash);
+        match tokio::fs::read(&path).await {
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Reading {}: {}", path.display(), e)),
+        }
+    }
+}
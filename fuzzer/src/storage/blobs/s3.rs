@@ -0,0 +1,214 @@
+//! Stores the Blobs in an S3-compatible Bucket
+//!
+//! The Requests are signed using AWS Signature Version 4 and use path-style URLs, like
+//! `<endpoint>/<bucket>/<prefix><hash>`, which are supported by AWS as well as by self-hosted
+//! Implementations like MinIO.
+
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::{Method, StatusCode, Url};
+use sha2::Sha256;
+
+use super::{check_hash, hash, BlobStore};
+
+/// The Headers that are part of the Signature
+const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+
+/// The Credentials used for signing the Requests
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub access_key: String,
+    pub secret_key: String,
+}
+
+/// A Blob-Store using an S3-compatible Bucket
+pub struct S3BlobStore {
+    client: reqwest::Client,
+    endpoint: Url,
+    bucket: String,
+    region: String,
+    credentials: Credentials,
+    /// Prepended to the Hash of every Blob, to share a Bucket with other Data
+    prefix: String,
+}
+
+impl S3BlobStore {
+    pub fn new(
+        endpoint: &str,
+        bucket: String,
+        region: String,
+        credentials: Credentials,
+    ) -> Result<Self, String> {
+        let endpoint = Url::parse(endpoint).map_err(|e| format!("Invalid S3-Endpoint: {}", e))?;
+        if endpoint.host_str().is_none() {
+            return Err("The S3-Endpoint needs a Host".to_string());
+        }
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            endpoint,
+            bucket,
+            region,
+            credentials,
+            prefix: String::new(),
+        })
+    }
+
+    /// Stores the Blobs under the Prefix, like `cfuzz/`
+    pub fn with_prefix(mut self, prefix: String) -> Self {
+        self.prefix = prefix;
+        self
+    }
+
+    /// Sends the signed Request for the Blob
+    async fn send(
+        &self,
+        method: Method,
+        blob: &str,
+        body: Vec<u8>,
+    ) -> Result<reqwest::Response, String> {
+        let path = format!(
+            "{}/{}/{}{}",
+            self.endpoint.path().trim_end_matches('/'),
+            encode(&self.bucket),
+            encode(&self.prefix),
+            blob
+        );
+        let mut url = self.endpoint.clone();
+        url.set_path(&path);
+
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap(), port),
+            None => url.host_str().unwrap().to_string(),
+        };
+        let payload_hash = hash(&body);
+        let date = amz_date(crate::now());
+
+        let signature = self.sign(&method, &path, &host, &payload_hash, &date);
+
+        self.client
+            .request(method, url)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", date)
+            .header("authorization", signature)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| format!("S3 Request: {}", e))
+    }
+
+    /// Builds the Authorization-Header for the Request
+    fn sign(
+        &self,
+        method: &Method,
+        path: &str,
+        host: &str,
+        payload_hash: &str,
+        date: &str,
+    ) -> String {
+        let day = &date[..8];
+        let scope = format!("{}/{}/s3/aws4_request", day, self.region);
+
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method, path, host, payload_hash, date, SIGNED_HEADERS, payload_hash
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            date,
+            scope,
+            hash(canonical_request.as_bytes())
+        );
+
+        let secret = format!("AWS4{}", self.credentials.secret_key);
+        let key = hmac(secret.as_bytes(), day.as_bytes());
+        let key = hmac(&key, self.region.as_bytes());
+        let key = hmac(&key, b"s3");
+        let key = hmac(&key, b"aws4_request");
+        let signature: String = hmac(&key, string_to_sign.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.credentials.access_key, scope, SIGNED_HEADERS, signature
+        )
+    }
+}
+
+#[async_trait]
+impl BlobStore for S3BlobStore {
+    async fn put(&self, hash: &str, content: &[u8]) -> Result<(), String> {
+        check_hash(hash)?;
+
+        let response = self.send(Method::PUT, hash, content.to_vec()).await?;
+        if !response.status().is_success() {
+            return Err(format!("Storing Blob {}: {}", hash, response.status()));
+        }
+
+        Ok(())
+    }
+
+    async fn get(&self, hash: &str) -> Result<Option<Vec<u8>>, String> {
+        check_hash(hash)?;
+
+        let response = self.send(Method::GET, hash, Vec::new()).await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => response
+                .bytes()
+                .await
+                .map(|b| Some(b.to_vec()))
+                .map_err(|e| format!("Loading Blob {}: {}", hash, e)),
+            status => Err(format!("Loading Blob {}: {}", hash, status)),
+        }
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// URI-encodes everything except the unreserved Characters and `/`, as required for the Path
+fn encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Formats the Time, in Seconds since the Unix-Epoch, like `20240131T235959Z`
+fn amz_date(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+
+    // Converts the Days since the Epoch into the Date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
@@ -0,0 +1,271 @@
+//! In-Memory Storage Backend
+//!
+//! Keeps everything in the Memory of the Process, so all the Data is lost once the Program exits.
+//! This is useful for Tests and ephemeral Deployments. The Data is stored like the Tables of the
+//! [`SqliteBackend`](super::sqlite::SqliteBackend), so both Backends behave the same.
+
+use crate::{
+    auth::{ApiToken, Member, Session, User},
+    project::{Project, Source, Target},
+    FuzzResult,
+};
+
+use super::{StorageBackend, StorageRequest, StorageResult};
+
+/// A Backend that stores everything in Memory
+#[derive(Debug, Default)]
+pub struct InMemoryBackend {
+    projects: Vec<(String, Source)>,
+    /// The Targets together with the Name of their Project
+    targets: Vec<(String, Target)>,
+    /// The Results together with the Name of their Project
+    results: Vec<(String, FuzzResult)>,
+    users: Vec<User>,
+    sessions: Vec<Session>,
+    tokens: Vec<ApiToken>,
+    members: Vec<Member>,
+}
+
+impl InMemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn handle(&mut self, req: StorageRequest) -> StorageResult {
+        match req {
+            StorageRequest::StoreResult {
+                project_name,
+                result,
+            } => {
+                self.results.push((project_name, result));
+
+                StorageResult::Store
+            }
+            StorageRequest::LoadResults { project } => {
+                let results = self
+                    .results
+                    .iter()
+                    .filter(|(p, _)| p == &project)
+                    .map(|(_, r)| r.clone())
+                    .collect();
+
+                StorageResult::LoadResults(results)
+            }
+            StorageRequest::StoreProject(project) => {
+                // Only the Project itself is stored, its Targets are managed separately
+                self.projects.retain(|(name, _)| name != &project.name);
+                self.projects.push((project.name, project.source));
+
+                StorageResult::StoreProject
+            }
+            StorageRequest::RemoveProject { name } => {
+                self.projects.retain(|(p, _)| p != &name);
+                self.results.retain(|(p, _)| p != &name);
+                self.targets.retain(|(p, _)| p != &name);
+                self.members.retain(|m| m.project != name);
+
+                StorageResult::RemoveProject
+            }
+            StorageRequest::LoadProjects => {
+                let projects = self
+                    .projects
+                    .iter()
+                    .map(|(name, source)| self.project(name, source))
+                    .collect();
+
+                StorageResult::LoadProjects(projects)
+            }
+            StorageRequest::LoadProject { name } => {
+                let project = self
+                    .projects
+                    .iter()
+                    .find(|(p, _)| p == &name)
+                    .map(|(name, source)| self.project(name, source));
+
+                StorageResult::LoadProject(project)
+            }
+            StorageRequest::AddProjectTarget {
+                project_name,
+                target,
+            } => {
+                self.targets
+                    .retain(|(p, t)| !(p == &project_name && t.name == target.name));
+                self.targets.push((project_name, *target));
+
+                StorageResult::AddProjectTarget
+            }
+            StorageRequest::UpdateProjectTarget {
+                project_name,
+                target,
+            } => {
+                let existing = self
+                    .targets
+                    .iter_mut()
+                    .find(|(p, t)| p == &project_name && t.name == target.name);
+                let updated = existing.is_some();
+                if let Some((_, t)) = existing {
+                    *t = *target;
+                }
+
+                StorageResult::UpdateProjectTarget(updated)
+            }
+            StorageRequest::LoadTarget {
+                project_name,
+                target_name,
+            } => {
+                let target = self
+                    .targets
+                    .iter()
+                    .find(|(p, t)| p == &project_name && t.name == target_name)
+                    .map(|(_, t)| Box::new(t.clone()));
+
+                StorageResult::LoadTarget(target)
+            }
+            StorageRequest::RemoveTarget {
+                project_name,
+                target_name,
+            } => {
+                self.targets
+                    .retain(|(p, t)| !(p == &project_name && t.name == target_name));
+
+                StorageResult::RemoveTarget
+            }
+            StorageRequest::StoreUser(user) => {
+                self.users.retain(|u| u.name != user.name);
+                self.users.push(user);
+
+                StorageResult::StoreUser
+            }
+            StorageRequest::LoadUser { name } => {
+                let user = self.users.iter().find(|u| u.name == name).cloned();
+
+                StorageResult::LoadUser(user)
+            }
+            StorageRequest::LoadUsers => StorageResult::LoadUsers(self.users.clone()),
+            StorageRequest::StoreSession(session) => {
+                self.sessions.retain(|s| s.token_hash != session.token_hash);
+                self.sessions.push(session);
+
+                StorageResult::StoreSession
+            }
+            StorageRequest::LoadSession { token_hash } => {
+                let session = self
+                    .sessions
+                    .iter()
+                    .find(|s| s.token_hash == token_hash)
+                    .cloned();
+
+                StorageResult::LoadSession(session)
+            }
+            StorageRequest::RemoveSession { token_hash } => {
+                self.sessions.retain(|s| s.token_hash != token_hash);
+
+                StorageResult::RemoveSession
+            }
+            StorageRequest::StoreToken(token) => {
+                // Both the Name per User and the Hash are unique
+                self.tokens.retain(|t| {
+                    !(t.user == token.user && t.name == token.name)
+                        && t.token_hash != token.token_hash
+                });
+                self.tokens.push(token);
+
+                StorageResult::StoreToken
+            }
+            StorageRequest::LoadToken { token_hash } => {
+                let token = self
+                    .tokens
+                    .iter()
+                    .find(|t| t.token_hash == token_hash)
+                    .cloned();
+
+                StorageResult::LoadToken(token)
+            }
+            StorageRequest::LoadTokens { user } => {
+                let tokens = self
+                    .tokens
+                    .iter()
+                    .filter(|t| t.user == user)
+                    .cloned()
+                    .collect();
+
+                StorageResult::LoadTokens(tokens)
+            }
+            StorageRequest::RemoveToken { user, name } => {
+                self.tokens.retain(|t| !(t.user == user && t.name == name));
+
+                StorageResult::RemoveToken
+            }
+            StorageRequest::StoreMember(member) => {
+                self.members
+                    .retain(|m| !(m.project == member.project && m.user == member.user));
+                self.members.push(member);
+
+                StorageResult::StoreMember
+            }
+            StorageRequest::RemoveMember { project, user } => {
+                self.members
+                    .retain(|m| !(m.project == project && m.user == user));
+
+                StorageResult::RemoveMember
+            }
+            StorageRequest::LoadMembers { project } => {
+                let members = self
+                    .members
+                    .iter()
+                    .filter(|m| m.project == project)
+                    .cloned()
+                    .collect();
+
+                StorageResult::LoadMembers(members)
+            }
+            StorageRequest::LoadMemberships { user } => {
+                let members = self
+                    .members
+                    .iter()
+                    .filter(|m| m.user == user)
+                    .cloned()
+                    .collect();
+
+                StorageResult::LoadMemberships(members)
+            }
+        }
+    }
+
+    /// Builds the Project together with all of its Targets
+    fn project(&self, name: &str, source: &Source) -> Project {
+        Project {
+            name: name.to_string(),
+            source: source.clone(),
+            targets: self
+                .targets
+                .iter()
+                .filter(|(p, _)| p == name)
+                .map(|(_, t)| t.clone())
+                .collect(),
+        }
+    }
+}
+
+impl StorageBackend for InMemoryBackend {
+    fn run(
+        mut self,
+        mut recv: tokio::sync::mpsc::Receiver<(
+            super::StorageRequest,
+            tokio::sync::oneshot::Sender<Result<super::StorageResult, super::StorageError>>,
+        )>,
+    ) {
+        std::thread::spawn(move || loop {
+            let (req, res_channel) = match recv.blocking_recv() {
+                Some(d) => d,
+                None => return,
+            };
+
+            let res = Ok(self.handle(req));
+
+            if res_channel.send(res).is_err() {
+                println!("Sending Result");
+            }
+        });
+    }
+}
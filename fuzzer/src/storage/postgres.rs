@@ -0,0 +1,642 @@
+//! PostgreSQL Storage Backend
+//!
+//! Uses the same Tables as the [`SqliteBackend`](super::sqlite::SqliteBackend), except that the
+//! `user` Columns are called `uname`, as `user` is reserved in PostgreSQL, and that the `results`
+//! Table has an additional `id` Column to keep the Results in the Order they were stored in.
+//!
+//! The Requests are handled concurrently using a Pool of Connections, so multiple Servers can share
+//! the same Database.
+//!
+//! # Migrations
+//! The Schema is created and updated using the [`MIGRATIONS`], where the Version of every applied
+//! Migration is stored in the `schema_migrations` Table. The Migrations are applied when connecting
+//! and every Migration is only ever applied once.
+
+use deadpool_postgres::{Manager, ManagerConfig, Pool, RecyclingMethod};
+use serde::Serialize;
+use tokio_postgres::{types::FromSql, NoTls, Row};
+
+use crate::{
+    auth::{ApiToken, Member, Session, User},
+    project::{Project, RunTarget, Source, Target},
+    FuzzResult,
+};
+
+use super::{StorageBackend, StorageError, StorageRequest, StorageResult};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// The Migrations of the Schema, where the Version of a Migration is its Index + 1.
+///
+/// Applied Migrations must never be changed, instead a new Migration needs to be added
+pub const MIGRATIONS: &[&str] = &["
+    CREATE TABLE projects (name text PRIMARY KEY, source text NOT NULL);
+    CREATE TABLE targets (
+        pname text NOT NULL,
+        name text NOT NULL,
+        folder text NOT NULL,
+        target text NOT NULL,
+        PRIMARY KEY (pname, name)
+    );
+    CREATE TABLE results (
+        id bigserial PRIMARY KEY,
+        pname text NOT NULL,
+        tname text NOT NULL,
+        input bytea NOT NULL,
+        sanitizer text
+    );
+    CREATE INDEX results_pname ON results (pname);
+    CREATE TABLE users (name text PRIMARY KEY, password_hash text NOT NULL, admin boolean NOT NULL);
+    CREATE TABLE sessions (token_hash text PRIMARY KEY, uname text NOT NULL, expires bigint NOT NULL);
+    CREATE TABLE tokens (
+        uname text NOT NULL,
+        name text NOT NULL,
+        token_hash text NOT NULL UNIQUE,
+        scopes text NOT NULL,
+        created bigint NOT NULL,
+        PRIMARY KEY (uname, name)
+    );
+    CREATE TABLE members (
+        pname text NOT NULL,
+        uname text NOT NULL,
+        role text NOT NULL,
+        PRIMARY KEY (pname, uname)
+    );
+", "
+    ALTER TABLE results ADD COLUMN blob text;
+", "
+    ALTER TABLE targets ADD COLUMN repeating boolean NOT NULL DEFAULT false;
+", "
+    ALTER TABLE targets
+        ADD COLUMN sanitizers text NOT NULL DEFAULT '[]',
+        ADD COLUMN workers bigint NOT NULL DEFAULT 1,
+        ADD COLUMN limits text NOT NULL DEFAULT '{}',
+        ADD COLUMN budget_secs bigint,
+        ADD COLUMN interval_secs bigint;
+"];
+
+/// A Backend that stores everything in a PostgreSQL Database
+#[derive(Clone)]
+pub struct PostgresBackend {
+    pool: Pool,
+}
+
+impl PostgresBackend {
+    /// Connects to the Database using the Connection-String and migrates it to the latest Schema
+    pub async fn connect(url: &str, max_connections: usize) -> Result<Self, String> {
+        let config: tokio_postgres::Config = url
+            .parse()
+            .map_err(|e| format!("Invalid Database-URL: {}", e))?;
+
+        Self::with_config(config, max_connections).await
+    }
+
+    /// Connects to the Database and migrates it to the latest Schema
+    pub async fn with_config(
+        config: tokio_postgres::Config,
+        max_connections: usize,
+    ) -> Result<Self, String> {
+        let manager = Manager::from_config(
+            config,
+            NoTls,
+            ManagerConfig {
+                recycling_method: RecyclingMethod::Fast,
+            },
+        );
+        let pool = Pool::builder(manager)
+            .max_size(max_connections)
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        let backend = Self { pool };
+        backend
+            .migrate()
+            .await
+            .map_err(|e| format!("Migrating the Database: {}", e))?;
+
+        Ok(backend)
+    }
+
+    /// Applies all the Migrations that were not applied yet
+    async fn migrate(&self) -> Result<(), Error> {
+        let mut client = self.pool.get().await?;
+        client
+            .batch_execute(
+                "CREATE TABLE IF NOT EXISTS schema_migrations (version integer PRIMARY KEY)",
+            )
+            .await?;
+
+        // The Lock makes sure that multiple Servers starting at the same Time do not apply the
+        // same Migration twice
+        let transaction = client.transaction().await?;
+        transaction
+            .batch_execute("LOCK TABLE schema_migrations IN EXCLUSIVE MODE")
+            .await?;
+
+        let row = transaction
+            .query_one(
+                "SELECT COALESCE(MAX(version), 0) FROM schema_migrations",
+                &[],
+            )
+            .await?;
+        let current: i32 = row.try_get(0)?;
+
+        for (version, migration) in (1..).zip(MIGRATIONS.iter()).skip(current as usize) {
+            transaction.batch_execute(migration).await?;
+            transaction
+                .execute(
+                    "INSERT INTO schema_migrations (version) VALUES ($1)",
+                    &[&version],
+                )
+                .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn handle(&self, req: StorageRequest) -> Result<StorageResult, StorageError> {
+        let mut client = self.pool.get().await?;
+
+        let result = match req {
+            StorageRequest::StoreResult {
+                project_name,
+                result,
+            } => {
+                let sanitizer = result.sanitizer.as_ref().map(encode).transpose()?;
+                client
+                    .execute(
+                        "INSERT INTO results (pname, tname, input, sanitizer, blob) VALUES ($1, $2, $3, $4, $5)",
+                        &[&project_name, &result.name, &result.content, &sanitizer, &result.blob],
+                    )
+                    .await?;
+
+                StorageResult::Store
+            }
+            StorageRequest::LoadResults { project } => {
+                let rows = client
+                    .query(
+                        "SELECT tname, input, sanitizer, blob FROM results WHERE pname=$1 ORDER BY id",
+                        &[&project],
+                    )
+                    .await?;
+
+                let results = rows
+                    .iter()
+                    .map(|row| {
+                        let raw_sanitizer: Option<&str> = get(row, "sanitizer")?;
+
+                        Ok(FuzzResult {
+                            name: get(row, "tname")?,
+                            content: get(row, "input")?,
+                            sanitizer: raw_sanitizer.map(serde_json::from_str).transpose()?,
+                            blob: get(row, "blob")?,
+                        })
+                    })
+                    .collect::<Result<_, StorageError>>()?;
+
+                StorageResult::LoadResults(results)
+            }
+            StorageRequest::StoreProject(project) => {
+                let src_str = encode(&project.source)?;
+                client
+                    .execute(
+                        "INSERT INTO projects (name, source) VALUES ($1, $2) ON CONFLICT (name) DO UPDATE SET source=EXCLUDED.source",
+                        &[&project.name, &src_str],
+                    )
+                    .await?;
+
+                StorageResult::StoreProject
+            }
+            StorageRequest::RemoveProject { name } => {
+                let transaction = client.transaction().await?;
+                for table in ["projects", "results", "targets", "members"] {
+                    let column = if table == "projects" { "name" } else { "pname" };
+                    transaction
+                        .execute(
+                            &format!("DELETE FROM {} WHERE {}=$1", table, column),
+                            &[&name],
+                        )
+                        .await?;
+                }
+                transaction.commit().await?;
+
+                StorageResult::RemoveProject
+            }
+            StorageRequest::LoadProjects => {
+                let rows = client
+                    .query("SELECT name, source FROM projects ORDER BY name", &[])
+                    .await?;
+                let target_rows = client
+                    .query(
+                        "SELECT pname, name, folder, target, repeating, sanitizers, workers, limits, budget_secs, interval_secs FROM targets ORDER BY pname, name",
+                        &[],
+                    )
+                    .await?;
+
+                let targets = target_rows
+                    .iter()
+                    .map(|t| Ok((get::<String>(t, "pname")?, Self::target(t)?)))
+                    .collect::<Result<Vec<_>, StorageError>>()?;
+
+                let projects = rows
+                    .iter()
+                    .map(|row| {
+                        let name: String = get(row, "name")?;
+                        let targets = targets
+                            .iter()
+                            .filter(|(pname, _)| *pname == name)
+                            .map(|(_, target)| target.clone())
+                            .collect();
+
+                        Self::project(row, targets)
+                    })
+                    .collect::<Result<_, StorageError>>()?;
+
+                StorageResult::LoadProjects(projects)
+            }
+            StorageRequest::LoadProject { name } => {
+                let row = client
+                    .query_opt("SELECT name, source FROM projects WHERE name=$1", &[&name])
+                    .await?;
+
+                let project = match row {
+                    Some(row) => {
+                        let targets = client
+                            .query(
+                                "SELECT name, folder, target, repeating, sanitizers, workers, limits, budget_secs, interval_secs FROM targets WHERE pname=$1 ORDER BY name",
+                                &[&name],
+                            )
+                            .await?
+                            .iter()
+                            .map(Self::target)
+                            .collect::<Result<_, _>>()?;
+
+                        Some(Self::project(&row, targets)?)
+                    }
+                    None => None,
+                };
+
+                StorageResult::LoadProject(project)
+            }
+            StorageRequest::AddProjectTarget {
+                project_name,
+                target,
+            } => {
+                let target_str = encode(&target.target)?;
+                let sanitizers_str = encode(&target.sanitizers)?;
+                let limits_str = encode(&target.limits)?;
+                client
+                    .execute(
+                        "INSERT INTO targets (pname, name, folder, target, repeating, sanitizers, workers, limits, budget_secs, interval_secs)
+                        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                        ON CONFLICT (pname, name) DO UPDATE SET folder=EXCLUDED.folder, target=EXCLUDED.target, repeating=EXCLUDED.repeating,
+                        sanitizers=EXCLUDED.sanitizers, workers=EXCLUDED.workers, limits=EXCLUDED.limits, budget_secs=EXCLUDED.budget_secs, interval_secs=EXCLUDED.interval_secs",
+                        &[
+                            &project_name,
+                            &target.name,
+                            &target.folder,
+                            &target_str,
+                            &target.repeating,
+                            &sanitizers_str,
+                            &(target.workers as i64),
+                            &limits_str,
+                            &target.budget_secs.map(|b| b as i64),
+                            &target.interval_secs.map(|i| i as i64),
+                        ],
+                    )
+                    .await?;
+
+                StorageResult::AddProjectTarget
+            }
+            StorageRequest::UpdateProjectTarget {
+                project_name,
+                target,
+            } => {
+                let target_str = encode(&target.target)?;
+                let sanitizers_str = encode(&target.sanitizers)?;
+                let limits_str = encode(&target.limits)?;
+                let updated = client
+                    .execute(
+                        "UPDATE targets SET folder=$3, target=$4, repeating=$5, sanitizers=$6, workers=$7, limits=$8, budget_secs=$9, interval_secs=$10
+                        WHERE pname=$1 AND name=$2",
+                        &[
+                            &project_name,
+                            &target.name,
+                            &target.folder,
+                            &target_str,
+                            &target.repeating,
+                            &sanitizers_str,
+                            &(target.workers as i64),
+                            &limits_str,
+                            &target.budget_secs.map(|b| b as i64),
+                            &target.interval_secs.map(|i| i as i64),
+                        ],
+                    )
+                    .await?;
+
+                StorageResult::UpdateProjectTarget(updated > 0)
+            }
+            StorageRequest::LoadTarget {
+                project_name,
+                target_name,
+            } => {
+                let row = client
+                    .query_opt(
+                        "SELECT name, folder, target, repeating, sanitizers, workers, limits, budget_secs, interval_secs FROM targets WHERE pname=$1 AND name=$2",
+                        &[&project_name, &target_name],
+                    )
+                    .await?;
+
+                let target = row.map(|r| Self::target(&r)).transpose()?;
+
+                StorageResult::LoadTarget(target.map(Box::new))
+            }
+            StorageRequest::RemoveTarget {
+                project_name,
+                target_name,
+            } => {
+                client
+                    .execute(
+                        "DELETE FROM targets WHERE pname=$1 AND name=$2",
+                        &[&project_name, &target_name],
+                    )
+                    .await?;
+
+                StorageResult::RemoveTarget
+            }
+            StorageRequest::StoreUser(user) => {
+                client
+                    .execute(
+                        "INSERT INTO users (name, password_hash, admin) VALUES ($1, $2, $3) ON CONFLICT (name) DO UPDATE SET password_hash=EXCLUDED.password_hash, admin=EXCLUDED.admin",
+                        &[&user.name, &user.password_hash, &user.admin],
+                    )
+                    .await?;
+
+                StorageResult::StoreUser
+            }
+            StorageRequest::LoadUser { name } => {
+                let row = client
+                    .query_opt(
+                        "SELECT name, password_hash, admin FROM users WHERE name=$1",
+                        &[&name],
+                    )
+                    .await?;
+
+                StorageResult::LoadUser(row.map(|r| Self::user(&r)).transpose()?)
+            }
+            StorageRequest::LoadUsers => {
+                let rows = client
+                    .query("SELECT name, password_hash, admin FROM users", &[])
+                    .await?;
+
+                StorageResult::LoadUsers(rows.iter().map(Self::user).collect::<Result<_, _>>()?)
+            }
+            StorageRequest::StoreSession(session) => {
+                client
+                    .execute(
+                        "INSERT INTO sessions (token_hash, uname, expires) VALUES ($1, $2, $3) ON CONFLICT (token_hash) DO UPDATE SET uname=EXCLUDED.uname, expires=EXCLUDED.expires",
+                        &[&session.token_hash, &session.user, &(session.expires as i64)],
+                    )
+                    .await?;
+
+                StorageResult::StoreSession
+            }
+            StorageRequest::LoadSession { token_hash } => {
+                let row = client
+                    .query_opt(
+                        "SELECT token_hash, uname, expires FROM sessions WHERE token_hash=$1",
+                        &[&token_hash],
+                    )
+                    .await?;
+
+                let session = row
+                    .map(|row| {
+                        Ok::<_, StorageError>(Session {
+                            token_hash: get(&row, "token_hash")?,
+                            user: get(&row, "uname")?,
+                            expires: get::<i64>(&row, "expires")? as u64,
+                        })
+                    })
+                    .transpose()?;
+
+                StorageResult::LoadSession(session)
+            }
+            StorageRequest::RemoveSession { token_hash } => {
+                client
+                    .execute("DELETE FROM sessions WHERE token_hash=$1", &[&token_hash])
+                    .await?;
+
+                StorageResult::RemoveSession
+            }
+            StorageRequest::StoreToken(token) => {
+                let scopes_str = encode(&token.scopes)?;
+
+                // Like in SQLite, the Token replaces all the Tokens it conflicts with
+                let transaction = client.transaction().await?;
+                transaction
+                    .execute(
+                        "DELETE FROM tokens WHERE (uname=$1 AND name=$2) OR token_hash=$3",
+                        &[&token.user, &token.name, &token.token_hash],
+                    )
+                    .await?;
+                transaction
+                    .execute(
+                        "INSERT INTO tokens (uname, name, token_hash, scopes, created) VALUES ($1, $2, $3, $4, $5)",
+                        &[&token.user, &token.name, &token.token_hash, &scopes_str, &(token.created as i64)],
+                    )
+                    .await?;
+                transaction.commit().await?;
+
+                StorageResult::StoreToken
+            }
+            StorageRequest::LoadToken { token_hash } => {
+                let row = client
+                    .query_opt(
+                        "SELECT uname, name, token_hash, scopes, created FROM tokens WHERE token_hash=$1",
+                        &[&token_hash],
+                    )
+                    .await?;
+
+                StorageResult::LoadToken(row.map(|r| Self::token(&r)).transpose()?)
+            }
+            StorageRequest::LoadTokens { user } => {
+                let rows = client
+                    .query(
+                        "SELECT uname, name, token_hash, scopes, created FROM tokens WHERE uname=$1",
+                        &[&user],
+                    )
+                    .await?;
+
+                StorageResult::LoadTokens(rows.iter().map(Self::token).collect::<Result<_, _>>()?)
+            }
+            StorageRequest::RemoveToken { user, name } => {
+                client
+                    .execute(
+                        "DELETE FROM tokens WHERE uname=$1 AND name=$2",
+                        &[&user, &name],
+                    )
+                    .await?;
+
+                StorageResult::RemoveToken
+            }
+            StorageRequest::StoreMember(member) => {
+                let role_str = encode(&member.role)?;
+                client
+                    .execute(
+                        "INSERT INTO members (pname, uname, role) VALUES ($1, $2, $3) ON CONFLICT (pname, uname) DO UPDATE SET role=EXCLUDED.role",
+                        &[&member.project, &member.user, &role_str],
+                    )
+                    .await?;
+
+                StorageResult::StoreMember
+            }
+            StorageRequest::RemoveMember { project, user } => {
+                client
+                    .execute(
+                        "DELETE FROM members WHERE pname=$1 AND uname=$2",
+                        &[&project, &user],
+                    )
+                    .await?;
+
+                StorageResult::RemoveMember
+            }
+            StorageRequest::LoadMembers { project } => {
+                let rows = client
+                    .query(
+                        "SELECT pname, uname, role FROM members WHERE pname=$1",
+                        &[&project],
+                    )
+                    .await?;
+
+                StorageResult::LoadMembers(rows.iter().map(Self::member).collect::<Result<_, _>>()?)
+            }
+            StorageRequest::LoadMemberships { user } => {
+                let rows = client
+                    .query(
+                        "SELECT pname, uname, role FROM members WHERE uname=$1",
+                        &[&user],
+                    )
+                    .await?;
+
+                StorageResult::LoadMemberships(
+                    rows.iter().map(Self::member).collect::<Result<_, _>>()?,
+                )
+            }
+        };
+
+        Ok(result)
+    }
+
+    fn project(row: &Row, targets: Vec<Target>) -> Result<Project, StorageError> {
+        let raw_source: &str = get(row, "source")?;
+
+        Ok(Project {
+            name: get(row, "name")?,
+            source: serde_json::from_str::<Source>(raw_source)?,
+            targets,
+        })
+    }
+
+    fn target(row: &Row) -> Result<Target, StorageError> {
+        let raw_target: &str = get(row, "target")?;
+        let raw_sanitizers: &str = get(row, "sanitizers")?;
+        let raw_limits: &str = get(row, "limits")?;
+
+        Ok(Target {
+            name: get(row, "name")?,
+            folder: get(row, "folder")?,
+            target: serde_json::from_str::<RunTarget>(raw_target)?,
+            repeating: get(row, "repeating")?,
+            sanitizers: serde_json::from_str(raw_sanitizers)?,
+            workers: get::<i64>(row, "workers")? as usize,
+            limits: serde_json::from_str(raw_limits)?,
+            budget_secs: get::<Option<i64>>(row, "budget_secs")?.map(|b| b as u64),
+            interval_secs: get::<Option<i64>>(row, "interval_secs")?.map(|i| i as u64),
+        })
+    }
+
+    fn user(row: &Row) -> Result<User, StorageError> {
+        Ok(User {
+            name: get(row, "name")?,
+            password_hash: get(row, "password_hash")?,
+            admin: get(row, "admin")?,
+        })
+    }
+
+    fn token(row: &Row) -> Result<ApiToken, StorageError> {
+        let raw_scopes: &str = get(row, "scopes")?;
+
+        Ok(ApiToken {
+            user: get(row, "uname")?,
+            name: get(row, "name")?,
+            token_hash: get(row, "token_hash")?,
+            scopes: serde_json::from_str(raw_scopes)?,
+            created: get::<i64>(row, "created")? as u64,
+        })
+    }
+
+    fn member(row: &Row) -> Result<Member, StorageError> {
+        let raw_role: &str = get(row, "role")?;
+
+        Ok(Member {
+            project: get(row, "pname")?,
+            user: get(row, "uname")?,
+            role: serde_json::from_str(raw_role)?,
+        })
+    }
+}
+
+/// Reads a Column, a Column of the wrong Type or an unexpected NULL is treated like corrupt Data
+fn get<'a, T>(row: &'a Row, column: &str) -> Result<T, StorageError>
+where
+    T: FromSql<'a>,
+{
+    row.try_get(column)
+        .map_err(|e| StorageError::Corrupt(format!("{}: {}", column, e)))
+}
+
+/// Encodes a JSON Column, a Value that can not be encoded is treated like corrupt Data
+fn encode<T>(value: &T) -> Result<String, StorageError>
+where
+    T: Serialize,
+{
+    Ok(serde_json::to_string(value)?)
+}
+
+impl From<tokio_postgres::Error> for StorageError {
+    fn from(e: tokio_postgres::Error) -> Self {
+        Self::Backend(e.to_string())
+    }
+}
+
+impl From<deadpool_postgres::PoolError> for StorageError {
+    fn from(e: deadpool_postgres::PoolError) -> Self {
+        Self::Backend(e.to_string())
+    }
+}
+
+impl StorageBackend for PostgresBackend {
+    fn run(
+        self,
+        mut recv: tokio::sync::mpsc::Receiver<(
+            super::StorageRequest,
+            tokio::sync::oneshot::Sender<Result<super::StorageResult, super::StorageError>>,
+        )>,
+    ) {
+        tokio::spawn(async move {
+            while let Some((req, res_channel)) = recv.recv().await {
+                // Every Request uses its own Connection from the Pool, so they run concurrently
+                let backend = self.clone();
+                tokio::spawn(async move {
+                    let res = backend.handle(req).await;
+
+                    if res_channel.send(res).is_err() {
+                        println!("Sending Result");
+                    }
+                });
+            }
+        });
+    }
+}
@@ -12,6 +12,12 @@
 //! ### name: String
 //! ### folder: String
 //! ### target: String
+//! ### repeating: Bool
+//! ### sanitizers: String
+//! ### workers: Integer
+//! ### limits: String
+//! ### budget_secs: Integer (nullable)
+//! ### interval_secs: Integer (nullable)
 //! ### Primary Key: (pname, name)
 //!
 //! ## `results` Table
@@ -19,17 +25,81 @@
 //! ### pname: String
 //! ### tname: String
 //! ### input: Binary
+//! ### blob: String (nullable)
+//! The Hash of the Input in the Blob-Store, in which case `input` is empty
+//! ### sanitizer: String (nullable)
+//!
+//! ## `users` Table
+//! Stores the Users
+//! ### name: String (primary key)
+//! ### password_hash: String
+//! ### admin: Bool
+//!
+//! ## `sessions` Table
+//! Stores the Sessions of the logged in Users
+//! ### token_hash: String (primary key)
+//! ### user: String
+//! ### expires: Integer
+//!
+//! ## `tokens` Table
+//! Stores the API-Tokens of the Users
+//! ### user: String
+//! ### name: String
+//! ### token_hash: String (unique)
+//! ### scopes: String
+//! ### created: Integer
+//! ### Primary Key: (user, name)
+//!
+//! ## `members` Table
+//! Stores the Roles of the Users in the Projects
+//! ### pname: String
+//! ### user: String
+//! ### role: String
+//! ### Primary Key: (pname, user)
+//!
+//! # Migrations
+//! The Schema is created and updated using the [`MIGRATIONS`], where the Version of the last applied
+//! Migration is stored as the `user_version` of the Database. Databases created before the
+//! Migrations existed have their Schema checked once, to find out which of the Migrations they
+//! already contain, and are then stamped with that Version.
 
 use std::path::Path;
 
-use rusqlite::Connection;
+use rusqlite::{Connection, OptionalExtension};
+use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
-    project::{Project, RunTarget, Source, Target},
+    auth::{ApiToken, Member, Session, User},
+    project::{Project, Source, Target},
     FuzzResult,
 };
 
-use super::{StorageBackend, StorageRequest, StorageResult};
+use super::{StorageBackend, StorageError, StorageRequest, StorageResult};
+
+/// The Migrations of the Schema, where the Version of a Migration is its Index + 1.
+///
+/// Applied Migrations must never be changed, instead a new Migration needs to be added
+pub const MIGRATIONS: &[&str] = &["
+    CREATE TABLE if not exists results (pname string, tname string, input binary);
+    CREATE TABLE if not exists projects (name string primary key, source string);
+    CREATE TABLE if not exists targets (pname string, name string, folder string, target string, PRIMARY KEY (pname, name));
+    CREATE TABLE if not exists users (name string primary key, password_hash string, admin bool);
+    CREATE TABLE if not exists sessions (token_hash string primary key, user string, expires integer);
+    CREATE TABLE if not exists tokens (user string, name string, token_hash string unique, scopes string, created integer, PRIMARY KEY (user, name));
+    CREATE TABLE if not exists members (pname string, user string, role string, PRIMARY KEY (pname, user));
+", "
+    ALTER TABLE results ADD COLUMN sanitizer string;
+", "
+    ALTER TABLE results ADD COLUMN blob string;
+", "
+    ALTER TABLE targets ADD COLUMN repeating bool NOT NULL DEFAULT 0;
+", "
+    ALTER TABLE targets ADD COLUMN sanitizers string NOT NULL DEFAULT '[]';
+    ALTER TABLE targets ADD COLUMN workers integer NOT NULL DEFAULT 1;
+    ALTER TABLE targets ADD COLUMN limits string NOT NULL DEFAULT '{}';
+    ALTER TABLE targets ADD COLUMN budget_secs integer;
+    ALTER TABLE targets ADD COLUMN interval_secs integer;
+"];
 
 /// A simple SQL-Lite Backend
 pub struct SqliteBackend {
@@ -38,140 +108,171 @@ pub struct SqliteBackend {
 }
 
 impl SqliteBackend {
-    pub fn new<F>(file: F) -> Self
+    /// Opens the Database in the File and migrates it to the latest Schema
+    pub fn new<F>(file: F) -> Result<Self, StorageError>
     where
         F: AsRef<Path>,
     {
-        Self {
-            connection: Connection::open(file).unwrap(),
+        let mut backend = Self {
+            connection: Connection::open(file)?,
+        };
+        backend.migrate()?;
+
+        Ok(backend)
+    }
+
+    /// Applies all the Migrations that were not applied yet, each in its own Transaction
+    fn migrate(&mut self) -> rusqlite::Result<()> {
+        let mut current: usize = self
+            .connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if current == 0 {
+            current = self.legacy_version()?;
+        }
+
+        for (version, migration) in (1..).zip(MIGRATIONS.iter()).skip(current) {
+            let transaction = self.connection.transaction()?;
+            transaction.execute_batch(migration)?;
+            transaction.pragma_update(None, "user_version", version)?;
+            transaction.commit()?;
+        }
+
+        Ok(())
+    }
+
+    /// Stamps the Version of a Database that was created before the Migrations existed.
+    ///
+    /// These Databases contain all of the Tables and the Columns that were added up to the
+    /// Version of cfuzz that last opened them, so the Columns they contain show which of the
+    /// Migrations they are on
+    fn legacy_version(&mut self) -> rusqlite::Result<usize> {
+        let columns = self
+            .connection
+            .prepare("SELECT name FROM pragma_table_info('results')")?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        if columns.is_empty() {
+            // A new Database
+            return Ok(0);
         }
+
+        let version = if columns.iter().any(|c| c == "blob") {
+            3
+        } else if columns.iter().any(|c| c == "sanitizer") {
+            2
+        } else {
+            1
+        };
+
+        // Older Databases may still miss some of the Tables, which the first Migration only
+        // creates if they do not exist yet
+        let transaction = self.connection.transaction()?;
+        transaction.execute_batch(MIGRATIONS[0])?;
+        transaction.pragma_update(None, "user_version", version)?;
+        transaction.commit()?;
+
+        Ok(version)
     }
 
-    fn handle(&self, req: StorageRequest) -> StorageResult {
-        match req {
+    fn handle(&mut self, req: StorageRequest) -> Result<StorageResult, StorageError> {
+        let result = match req {
             StorageRequest::StoreResult {
                 project_name,
                 result,
             } => {
+                let sanitizer = result.sanitizer.as_ref().map(encode).transpose()?;
                 self.connection
                             .execute(
-                                "INSERT INTO results (pname, tname, input) VALUES (:pname, :tname, :data)",
-                                rusqlite::named_params![":pname": project_name, ":tname": result.name, ":data": result.content],
-                            )
-                            .unwrap();
+                                "INSERT INTO results (pname, tname, input, sanitizer, blob) VALUES (:pname, :tname, :data, :sanitizer, :blob)",
+                                rusqlite::named_params![":pname": project_name, ":tname": result.name, ":data": result.content, ":sanitizer": sanitizer, ":blob": result.blob],
+                            )?;
 
                 StorageResult::Store
             }
             StorageRequest::LoadResults { project } => {
-                let mut preped = self
-                    .connection
-                    .prepare("SELECT tname, input FROM results WHERE pname=:pname")
-                    .unwrap();
+                let mut preped = self.connection.prepare(
+                    "SELECT tname, input, sanitizer, blob FROM results WHERE pname=:pname",
+                )?;
 
                 let results = preped
                     .query_map(rusqlite::named_params! { ":pname": project }, |row| {
                         let name: String = row.get("tname")?;
                         let input: Vec<u8> = row.get("input")?;
+                        let raw_sanitizer: Option<String> = row.get("sanitizer")?;
 
                         Ok(FuzzResult {
                             name,
                             content: input,
+                            sanitizer: raw_sanitizer.as_deref().map(decode).transpose()?,
+                            blob: row.get("blob")?,
                         })
-                    })
-                    .unwrap()
-                    .filter_map(|r| r.ok());
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
 
-                StorageResult::LoadResults(results.collect())
+                StorageResult::LoadResults(results)
             }
             StorageRequest::StoreProject(project) => {
-                let src_str = serde_json::to_string(&project.source).unwrap();
-                self.connection
-                    .execute(
-                        "INSERT OR REPLACE INTO projects (name, source) VALUES (:name, :source)",
-                        rusqlite::named_params![":name": project.name, ":source": src_str],
-                    )
-                    .unwrap();
+                let src_str = encode(&project.source)?;
+                self.connection.execute(
+                    "INSERT OR REPLACE INTO projects (name, source) VALUES (:name, :source)",
+                    rusqlite::named_params![":name": project.name, ":source": src_str],
+                )?;
 
                 StorageResult::StoreProject
             }
             StorageRequest::RemoveProject { name } => {
-                self.connection
-                    .execute(
-                        "DELETE FROM projects WHERE name=:pname",
-                        rusqlite::named_params! {":pname": name},
-                    )
-                    .unwrap();
-
-                self.connection
-                    .execute(
-                        "DELETE FROM results WHERE pname=:pname",
-                        rusqlite::named_params! {":pname": name},
-                    )
-                    .unwrap();
-
-                self.connection
-                    .execute(
-                        "DELETE FROM targets WHERE pname=:pname",
-                        rusqlite::named_params! {":pname": name},
-                    )
-                    .unwrap();
+                // Either everything of the Project is removed or nothing
+                let transaction = self.connection.transaction()?;
+                for statement in [
+                    "DELETE FROM projects WHERE name=:pname",
+                    "DELETE FROM results WHERE pname=:pname",
+                    "DELETE FROM targets WHERE pname=:pname",
+                    "DELETE FROM members WHERE pname=:pname",
+                ] {
+                    transaction.execute(statement, rusqlite::named_params! {":pname": name})?;
+                }
+                transaction.commit()?;
 
                 StorageResult::RemoveProject
             }
             StorageRequest::LoadProjects => {
                 let mut preped = self
                     .connection
-                    .prepare("SELECT name, source FROM projects")
-                    .unwrap();
+                    .prepare("SELECT name, source FROM projects")?;
 
                 let mut preped_targets = self
                     .connection
-                    .prepare("SELECT name, folder, target FROM targets WHERE pname=:pname")
-                    .unwrap();
+                    .prepare(
+                        "SELECT name, folder, target, repeating, sanitizers, workers, limits, budget_secs, interval_secs FROM targets WHERE pname=:pname",
+                    )?;
 
                 let results = preped
                     .query_map([], |row| {
                         let name: String = row.get("name")?;
                         let raw_source: String = row.get("source")?;
 
-                        let source: Source = serde_json::from_str(&raw_source).unwrap();
+                        let source: Source = decode(&raw_source)?;
 
                         let targets = preped_targets
-                            .query_map(rusqlite::named_params! { ":pname": name }, |row| {
-                                let t_name: String = row.get("name")?;
-                                let t_folder: String = row.get("folder")?;
-                                let raw_t_target: String = row.get("target")?;
-
-                                let t_target: RunTarget =
-                                    serde_json::from_str(&raw_t_target).unwrap();
-
-                                Ok(Target {
-                                    name: t_name,
-                                    folder: t_folder,
-                                    target: t_target,
-                                    repeating: false,
-                                })
-                            })
-                            .unwrap()
-                            .filter_map(|r| r.ok())
-                            .collect();
+                            .query_map(rusqlite::named_params! { ":pname": name }, Self::target)?
+                            .collect::<rusqlite::Result<_>>()?;
 
                         Ok(Project {
                             name,
                             source,
                             targets,
                         })
-                    })
-                    .unwrap()
-                    .filter_map(|r| r.ok());
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
 
-                StorageResult::LoadProjects(results.collect())
+                StorageResult::LoadProjects(results)
             }
             StorageRequest::LoadProject { name } => {
                 let mut preped_targets = self
                     .connection
-                    .prepare("SELECT name, folder, target FROM targets WHERE pname=:pname")
-                    .unwrap();
+                    .prepare(
+                        "SELECT name, folder, target, repeating, sanitizers, workers, limits, budget_secs, interval_secs FROM targets WHERE pname=:pname",
+                    )?;
 
                 let result = self.connection.query_row(
                     "SELECT name, source FROM projects where name=:pname",
@@ -182,27 +283,11 @@ impl SqliteBackend {
                         let name: String = row.get("name")?;
                         let raw_source: String = row.get("source")?;
 
-                        let source: Source = serde_json::from_str(&raw_source).unwrap();
+                        let source: Source = decode(&raw_source)?;
 
                         let targets = preped_targets
-                            .query_map(rusqlite::named_params! { ":pname": name }, |row| {
-                                let t_name: String = row.get("name")?;
-                                let t_folder: String = row.get("folder")?;
-                                let raw_t_target: String = row.get("target")?;
-
-                                let t_target: RunTarget =
-                                    serde_json::from_str(&raw_t_target).unwrap();
-
-                                Ok(Target {
-                                    name: t_name,
-                                    folder: t_folder,
-                                    target: t_target,
-                                    repeating: false,
-                                })
-                            })
-                            .unwrap()
-                            .filter_map(|r| r.ok())
-                            .collect();
+                            .query_map(rusqlite::named_params! { ":pname": name }, Self::target)?
+                            .collect::<rusqlite::Result<_>>()?;
 
                         Ok(Project {
                             name,
@@ -212,69 +297,281 @@ impl SqliteBackend {
                     },
                 );
 
-                StorageResult::LoadProject(result.ok())
+                StorageResult::LoadProject(result.optional()?)
             }
             StorageRequest::AddProjectTarget {
                 project_name,
                 target,
             } => {
-                let target_str = serde_json::to_string(&target.target).unwrap();
+                let target_str = encode(&target.target)?;
                 self.connection.execute(
-                            "INSERT OR REPLACE INTO targets (pname, name, folder, target) VALUES (:pname, :target_name, :target_folder, :target_target)", 
-                            rusqlite::named_params! { ":pname": project_name, ":target_name": target.name, ":target_folder": target.folder, ":target_target": target_str }).unwrap();
+                            "INSERT OR REPLACE INTO targets (pname, name, folder, target, repeating, sanitizers, workers, limits, budget_secs, interval_secs) VALUES (:pname, :target_name, :target_folder, :target_target, :repeating, :sanitizers, :workers, :limits, :budget_secs, :interval_secs)",
+                            rusqlite::named_params! {
+                                ":pname": project_name,
+                                ":target_name": target.name,
+                                ":target_folder": target.folder,
+                                ":target_target": target_str,
+                                ":repeating": target.repeating,
+                                ":sanitizers": encode(&target.sanitizers)?,
+                                ":workers": target.workers,
+                                ":limits": encode(&target.limits)?,
+                                ":budget_secs": target.budget_secs,
+                                ":interval_secs": target.interval_secs,
+                            })?;
 
                 StorageResult::AddProjectTarget
             }
+            StorageRequest::UpdateProjectTarget {
+                project_name,
+                target,
+            } => {
+                let updated = self.connection.execute(
+                            "UPDATE targets SET folder=:target_folder, target=:target_target, repeating=:repeating, sanitizers=:sanitizers, workers=:workers, limits=:limits, budget_secs=:budget_secs, interval_secs=:interval_secs WHERE pname=:pname AND name=:target_name",
+                            rusqlite::named_params! {
+                                ":pname": project_name,
+                                ":target_name": target.name,
+                                ":target_folder": target.folder,
+                                ":target_target": encode(&target.target)?,
+                                ":repeating": target.repeating,
+                                ":sanitizers": encode(&target.sanitizers)?,
+                                ":workers": target.workers,
+                                ":limits": encode(&target.limits)?,
+                                ":budget_secs": target.budget_secs,
+                                ":interval_secs": target.interval_secs,
+                            })?;
+
+                StorageResult::UpdateProjectTarget(updated > 0)
+            }
             StorageRequest::LoadTarget {
                 project_name,
                 target_name,
             } => {
-                dbg!(&project_name, &target_name);
+                let result = self.connection.query_row(
+                    "SELECT name, folder, target, repeating, sanitizers, workers, limits, budget_secs, interval_secs FROM targets WHERE pname=:pname AND name=:target_name",
+                    rusqlite::named_params! {
+                        ":pname": project_name,
+                        ":target_name": target_name,
+                    },
+                    Self::target,
+                );
 
-                todo!("Load Target")
+                StorageResult::LoadTarget(result.optional()?.map(Box::new))
             }
             StorageRequest::RemoveTarget {
                 project_name,
                 target_name,
             } => {
+                self.connection.execute(
+                    "DELETE FROM targets WHERE pname=:pname AND name=:target_name",
+                    rusqlite::named_params! {
+                        ":pname": project_name,
+                        ":target_name": target_name,
+                    },
+                )?;
+
+                StorageResult::RemoveTarget
+            }
+            StorageRequest::StoreUser(user) => {
                 self.connection
                     .execute(
-                        "DELETE FROM targets WHERE pname=:pname AND name=:target_name",
-                        rusqlite::named_params! {
-                            ":pname": project_name,
-                            ":target_name": target_name,
-                        },
-                    )
-                    .unwrap();
+                        "INSERT OR REPLACE INTO users (name, password_hash, admin) VALUES (:name, :hash, :admin)",
+                        rusqlite::named_params! { ":name": user.name, ":hash": user.password_hash, ":admin": user.admin },
+                    )?;
 
-                StorageResult::RemoveTarget
+                StorageResult::StoreUser
             }
-        }
+            StorageRequest::LoadUser { name } => {
+                let result = self.connection.query_row(
+                    "SELECT name, password_hash, admin FROM users WHERE name=:name",
+                    rusqlite::named_params! { ":name": name },
+                    Self::user,
+                );
+
+                StorageResult::LoadUser(result.optional()?)
+            }
+            StorageRequest::LoadUsers => {
+                let mut preped = self
+                    .connection
+                    .prepare("SELECT name, password_hash, admin FROM users")?;
+
+                let results = preped
+                    .query_map([], Self::user)?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+
+                StorageResult::LoadUsers(results)
+            }
+            StorageRequest::StoreSession(session) => {
+                self.connection
+                    .execute(
+                        "INSERT OR REPLACE INTO sessions (token_hash, user, expires) VALUES (:hash, :user, :expires)",
+                        rusqlite::named_params! { ":hash": session.token_hash, ":user": session.user, ":expires": session.expires },
+                    )?;
+
+                StorageResult::StoreSession
+            }
+            StorageRequest::LoadSession { token_hash } => {
+                let result = self.connection.query_row(
+                    "SELECT token_hash, user, expires FROM sessions WHERE token_hash=:hash",
+                    rusqlite::named_params! { ":hash": token_hash },
+                    |row| {
+                        Ok(Session {
+                            token_hash: row.get("token_hash")?,
+                            user: row.get("user")?,
+                            expires: row.get("expires")?,
+                        })
+                    },
+                );
+
+                StorageResult::LoadSession(result.optional()?)
+            }
+            StorageRequest::RemoveSession { token_hash } => {
+                self.connection.execute(
+                    "DELETE FROM sessions WHERE token_hash=:hash",
+                    rusqlite::named_params! { ":hash": token_hash },
+                )?;
+
+                StorageResult::RemoveSession
+            }
+            StorageRequest::StoreToken(token) => {
+                let scopes_str = encode(&token.scopes)?;
+                self.connection
+                    .execute(
+                        "INSERT OR REPLACE INTO tokens (user, name, token_hash, scopes, created) VALUES (:user, :name, :hash, :scopes, :created)",
+                        rusqlite::named_params! { ":user": token.user, ":name": token.name, ":hash": token.token_hash, ":scopes": scopes_str, ":created": token.created },
+                    )?;
+
+                StorageResult::StoreToken
+            }
+            StorageRequest::LoadToken { token_hash } => {
+                let result = self.connection.query_row(
+                    "SELECT user, name, token_hash, scopes, created FROM tokens WHERE token_hash=:hash",
+                    rusqlite::named_params! { ":hash": token_hash },
+                    Self::token,
+                );
+
+                StorageResult::LoadToken(result.optional()?)
+            }
+            StorageRequest::LoadTokens { user } => {
+                let mut preped = self.connection.prepare(
+                    "SELECT user, name, token_hash, scopes, created FROM tokens WHERE user=:user",
+                )?;
+
+                let results = preped
+                    .query_map(rusqlite::named_params! { ":user": user }, Self::token)?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+
+                StorageResult::LoadTokens(results)
+            }
+            StorageRequest::RemoveToken { user, name } => {
+                self.connection.execute(
+                    "DELETE FROM tokens WHERE user=:user AND name=:name",
+                    rusqlite::named_params! { ":user": user, ":name": name },
+                )?;
+
+                StorageResult::RemoveToken
+            }
+            StorageRequest::StoreMember(member) => {
+                let role_str = encode(&member.role)?;
+                self.connection
+                    .execute(
+                        "INSERT OR REPLACE INTO members (pname, user, role) VALUES (:pname, :user, :role)",
+                        rusqlite::named_params! { ":pname": member.project, ":user": member.user, ":role": role_str },
+                    )?;
+
+                StorageResult::StoreMember
+            }
+            StorageRequest::RemoveMember { project, user } => {
+                self.connection.execute(
+                    "DELETE FROM members WHERE pname=:pname AND user=:user",
+                    rusqlite::named_params! { ":pname": project, ":user": user },
+                )?;
+
+                StorageResult::RemoveMember
+            }
+            StorageRequest::LoadMembers { project } => {
+                let mut preped = self
+                    .connection
+                    .prepare("SELECT pname, user, role FROM members WHERE pname=:pname")?;
+
+                let results = preped
+                    .query_map(rusqlite::named_params! { ":pname": project }, Self::member)?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+
+                StorageResult::LoadMembers(results)
+            }
+            StorageRequest::LoadMemberships { user } => {
+                let mut preped = self
+                    .connection
+                    .prepare("SELECT pname, user, role FROM members WHERE user=:user")?;
+
+                let results = preped
+                    .query_map(rusqlite::named_params! { ":user": user }, Self::member)?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+
+                StorageResult::LoadMemberships(results)
+            }
+        };
+
+        Ok(result)
+    }
+
+    fn target(row: &rusqlite::Row) -> rusqlite::Result<Target> {
+        let raw_target: String = row.get("target")?;
+        let raw_sanitizers: String = row.get("sanitizers")?;
+        let raw_limits: String = row.get("limits")?;
+
+        Ok(Target {
+            name: row.get("name")?,
+            folder: row.get("folder")?,
+            target: decode(&raw_target)?,
+            repeating: row.get("repeating")?,
+            sanitizers: decode(&raw_sanitizers)?,
+            workers: row.get("workers")?,
+            limits: decode(&raw_limits)?,
+            budget_secs: row.get("budget_secs")?,
+            interval_secs: row.get("interval_secs")?,
+        })
+    }
+
+    fn member(row: &rusqlite::Row) -> rusqlite::Result<Member> {
+        let raw_role: String = row.get("role")?;
+
+        Ok(Member {
+            project: row.get("pname")?,
+            user: row.get("user")?,
+            role: decode(&raw_role)?,
+        })
+    }
+
+    fn user(row: &rusqlite::Row) -> rusqlite::Result<User> {
+        Ok(User {
+            name: row.get("name")?,
+            password_hash: row.get("password_hash")?,
+            admin: row.get("admin")?,
+        })
+    }
+
+    fn token(row: &rusqlite::Row) -> rusqlite::Result<ApiToken> {
+        let raw_scopes: String = row.get("scopes")?;
+
+        Ok(ApiToken {
+            user: row.get("user")?,
+            name: row.get("name")?,
+            token_hash: row.get("token_hash")?,
+            scopes: decode(&raw_scopes)?,
+            created: row.get("created")?,
+        })
     }
 }
 
 impl StorageBackend for SqliteBackend {
     fn run(
-        self,
+        mut self,
         mut recv: tokio::sync::mpsc::Receiver<(
             super::StorageRequest,
-            tokio::sync::oneshot::Sender<super::StorageResult>,
+            tokio::sync::oneshot::Sender<Result<super::StorageResult, super::StorageError>>,
         )>,
     ) {
-        self.connection
-            .execute(
-                "CREATE TABLE if not exists results (pname string, tname string, input binary)",
-                [],
-            )
-            .expect("");
-        self.connection
-            .execute(
-                "CREATE TABLE if not exists projects (name string primary key, source string)",
-                [],
-            )
-            .expect("");
-        self.connection.execute("CREATE TABLE if not exists targets (pname string, name string, folder string, target string, PRIMARY KEY (pname, name))", []).expect("");
-
         std::thread::spawn(move || loop {
             let (req, res_channel) = match recv.blocking_recv() {
                 Some(d) => d,
@@ -289,3 +586,30 @@ impl StorageBackend for SqliteBackend {
         });
     }
 }
+
+/// Encodes a JSON Column, a Value that can not be encoded is treated like corrupt Data
+fn encode<T>(value: &T) -> Result<String, StorageError>
+where
+    T: Serialize,
+{
+    serde_json::to_string(value).map_err(|e| StorageError::Corrupt(e.to_string()))
+}
+
+/// Decodes a JSON Column, a Column that can not be decoded is treated like a Column of the wrong Type
+fn decode<T>(raw: &str) -> rusqlite::Result<T>
+where
+    T: DeserializeOwned,
+{
+    serde_json::from_str(raw).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e {
+            rusqlite::Error::FromSqlConversionFailure(_, _, e) => Self::Corrupt(e.to_string()),
+            e => Self::Backend(e.to_string()),
+        }
+    }
+}
@@ -1,7 +1,8 @@
-
+use serde::{Deserialize, Serialize};
 
 use crate::project::{Source, Target};
 
+#[derive(Debug, Serialize, Deserialize)]
 pub struct FuzzTarget {
     pname: String,
     name: String,
@@ -35,4 +36,7 @@ impl FuzzTarget {
     pub fn runner(&self) -> &Target {
         &self.runner
     }
+    pub fn set_runner(&mut self, runner: Target) {
+        self.runner = runner;
+    }
 }
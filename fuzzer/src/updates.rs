@@ -0,0 +1,92 @@
+//! Live Updates about everything that happens, which are pushed to the connected Clients
+
+use futures_util::Stream;
+use serde::Serialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::{project::Sanitizer, runs::RunEvent};
+
+/// The Number of Updates that are buffered for slow Subscribers, before they start to miss some
+const CAPACITY: usize = 256;
+
+/// A single Update
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type")]
+pub enum Update {
+    /// A new Run of a Target was started
+    RunStarted {
+        run: u64,
+        project: String,
+        target: String,
+        sanitizer: Option<Sanitizer>,
+    },
+    /// The Runner reported some Progress for a Run
+    Run(RunEvent),
+    /// A new Crash was stored for the Project
+    ResultStored {
+        project: String,
+        target: String,
+        sanitizer: Option<Sanitizer>,
+    },
+    /// The Project or one of its Targets was added or changed
+    ProjectChanged { project: String },
+    /// The Project was removed
+    ProjectRemoved { project: String },
+}
+
+impl Update {
+    /// The Project the Update belongs to
+    pub fn project(&self) -> &str {
+        match self {
+            Update::RunStarted { project, .. } => project,
+            Update::Run(event) => &event.project,
+            Update::ResultStored { project, .. } => project,
+            Update::ProjectChanged { project } => project,
+            Update::ProjectRemoved { project } => project,
+        }
+    }
+}
+
+/// Distributes the Updates to all the Subscribers
+#[derive(Debug, Clone)]
+pub struct Updates {
+    sender: broadcast::Sender<Update>,
+}
+
+impl Default for Updates {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Updates {
+    pub fn new() -> Self {
+        Self {
+            sender: broadcast::channel(CAPACITY).0,
+        }
+    }
+
+    /// Sends the Update to all the current Subscribers
+    pub fn send(&self, update: Update) {
+        // There might not be any Subscribers at the Moment
+        let _ = self.sender.send(update);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Update> {
+        self.sender.subscribe()
+    }
+
+    /// A Stream of all the Updates from now on, Updates that were missed because the Subscriber
+    /// was too slow are skipped
+    pub fn stream(&self) -> impl Stream<Item = Update> {
+        futures_util::stream::unfold(self.subscribe(), |mut recv| async move {
+            loop {
+                match recv.recv().await {
+                    Ok(update) => return Some((update, recv)),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,209 @@
+//! Checks the Blob-Stores, the S3 Blob-Store is checked against a minimal S3 stand-in that keeps
+//! the Objects in Memory
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::{Arc, Mutex},
+};
+
+use cfuzz::storage::blobs::{
+    hash,
+    s3::{Credentials, S3BlobStore},
+    BlobStore, FsBlobStore,
+};
+
+type Objects = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+/// Starts the S3 stand-in, returning its Endpoint and the stored Objects by their Path
+fn fake_s3() -> (String, Objects) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let objects: Objects = Arc::default();
+
+    let stored = objects.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let mut parts = line.split_whitespace();
+            let method = parts.next().unwrap().to_string();
+            let path = parts.next().unwrap().to_string();
+
+            let mut headers = HashMap::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                let (name, value) = line.split_once(':').unwrap();
+                headers.insert(name.to_lowercase(), value.trim().to_string());
+            }
+
+            let length = headers
+                .get("content-length")
+                .map(|l| l.parse().unwrap())
+                .unwrap_or(0);
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+
+            let authorized = headers.get("authorization").is_some_and(|a| {
+                a.starts_with("AWS4-HMAC-SHA256 Credential=access/")
+                    && a.contains("/us-east-1/s3/aws4_request")
+                    && a.contains("SignedHeaders=host;x-amz-content-sha256;x-amz-date")
+            });
+            let payload_matches = headers.get("x-amz-content-sha256") == Some(&hash(&body));
+
+            let (status, content) = if !authorized || !payload_matches {
+                ("403 Forbidden", Vec::new())
+            } else if method == "PUT" {
+                stored.lock().unwrap().insert(path, body);
+                ("200 OK", Vec::new())
+            } else {
+                match stored.lock().unwrap().get(&path) {
+                    Some(c) => ("200 OK", c.clone()),
+                    None => ("404 Not Found", Vec::new()),
+                }
+            };
+
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                status,
+                content.len()
+            );
+            let _ = stream.write_all(&content);
+        }
+    });
+
+    (endpoint, objects)
+}
+
+fn credentials() -> Credentials {
+    Credentials {
+        access_key: "access".to_string(),
+        secret_key: "secret".to_string(),
+    }
+}
+
+/// The Checks every Blob-Store has to pass
+async fn roundtrip(store: &dyn BlobStore) {
+    let content = b"crashing input".to_vec();
+    let blob = hash(&content);
+
+    assert_eq!(None, store.get(&blob).await.unwrap());
+
+    store.put(&blob, &content).await.unwrap();
+    // Storing the same Blob again is fine
+    store.put(&blob, &content).await.unwrap();
+    assert_eq!(Some(content), store.get(&blob).await.unwrap());
+
+    let empty = hash(&[]);
+    store.put(&empty, &[]).await.unwrap();
+    assert_eq!(Some(Vec::new()), store.get(&empty).await.unwrap());
+
+    assert!(store.get("../data.db").await.is_err());
+    assert!(store.put("not-a-hash", b"").await.is_err());
+}
+
+#[test]
+fn hash_is_sha256() {
+    assert_eq!(
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+        hash(&[])
+    );
+}
+
+#[tokio::test]
+async fn filesystem() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = FsBlobStore::new(dir.path());
+    roundtrip(&store).await;
+
+    let blob = hash(b"crashing input");
+    assert!(dir.path().join(&blob[..2]).join(&blob).is_file());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn filesystem_concurrent() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = Arc::new(FsBlobStore::new(dir.path()));
+    let content = vec![7; 1024 * 1024];
+    let blob = hash(&content);
+
+    let puts: Vec<_> = (0..16)
+        .map(|_| {
+            let store = store.clone();
+            let (blob, content) = (blob.clone(), content.clone());
+            tokio::spawn(async move { store.put(&blob, &content).await })
+        })
+        .collect();
+    for put in puts {
+        put.await.unwrap().unwrap();
+    }
+
+    assert_eq!(Some(content), store.get(&blob).await.unwrap());
+    // No temporary Files are left behind
+    assert_eq!(
+        1,
+        std::fs::read_dir(dir.path().join(&blob[..2]))
+            .unwrap()
+            .count()
+    );
+}
+
+#[tokio::test]
+async fn s3() {
+    let (endpoint, objects) = fake_s3();
+    let store = S3BlobStore::new(
+        &endpoint,
+        "bucket".to_string(),
+        "us-east-1".to_string(),
+        credentials(),
+    )
+    .unwrap()
+    .with_prefix("cfuzz/".to_string());
+    roundtrip(&store).await;
+
+    let blob = hash(b"crashing input");
+    assert_eq!(
+        Some(&b"crashing input".to_vec()),
+        objects
+            .lock()
+            .unwrap()
+            .get(&format!("/bucket/cfuzz/{}", blob))
+    );
+}
+
+#[tokio::test]
+async fn s3_rejected() {
+    let (endpoint, _) = fake_s3();
+    let store = S3BlobStore::new(
+        &endpoint,
+        "bucket".to_string(),
+        "eu-central-1".to_string(),
+        credentials(),
+    )
+    .unwrap();
+
+    let blob = hash(b"crashing input");
+    assert!(store.put(&blob, b"crashing input").await.is_err());
+    assert!(store.get(&blob).await.is_err());
+}
+
+#[test]
+fn s3_endpoint() {
+    assert!(S3BlobStore::new(
+        "not a url",
+        "bucket".to_string(),
+        "us-east-1".to_string(),
+        credentials()
+    )
+    .is_err());
+}
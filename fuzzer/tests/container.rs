@@ -0,0 +1,316 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Write},
+    os::unix::fs::MetadataExt,
+    os::unix::net::UnixListener,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Arc, Mutex},
+};
+
+use cfuzz::{
+    project::{Source, Target},
+    runner::{container::ContainerRunner, Event, RunOutput, Runner, Stop},
+    FuzzTarget,
+};
+use tokio::sync::{mpsc, oneshot};
+
+/// A stand-in for the Docker API that records all the Requests and simulates the Containers
+struct FakeDocker {
+    requests: Arc<Mutex<Vec<String>>>,
+    /// The Specs of all the created Containers
+    specs: Arc<Mutex<Vec<serde_json::Value>>>,
+}
+
+impl FakeDocker {
+    /// Starts the Server, the Fuzzer Containers write a Crash into the mounted Workspace and then
+    /// either exit right away or keep running until they are stopped
+    fn start(socket: &Path, fuzzer_exits: bool) -> Self {
+        let listener = UnixListener::bind(socket).unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let specs = Arc::new(Mutex::new(Vec::new()));
+
+        let log = requests.clone();
+        let created = specs.clone();
+        std::thread::spawn(move || {
+            let mut containers: HashMap<String, (serde_json::Value, bool)> = HashMap::new();
+
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" {
+                        break;
+                    }
+                    if let Some(len) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        content_length = len.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+
+                let mut parts = request_line.split(' ');
+                let method = parts.next().unwrap().to_string();
+                let path = parts
+                    .next()
+                    .unwrap()
+                    .trim_start_matches("/v1.41")
+                    .to_string();
+                log.lock().unwrap().push(format!("{} {}", method, path));
+
+                let segments: Vec<_> = path.split(['/', '?']).collect();
+                let (status, response) = match (method.as_str(), &segments[1..]) {
+                    ("POST", ["containers", "create"]) => {
+                        let id = format!("c{}", containers.len());
+                        let spec: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                        created.lock().unwrap().push(spec.clone());
+                        containers.insert(id.clone(), (spec, true));
+                        (201, serde_json::json!({ "Id": id }).to_string())
+                    }
+                    ("POST", ["containers", id, "start"]) => {
+                        let (spec, running) = containers.get_mut(*id).unwrap();
+                        let cmd: Vec<_> = spec["Cmd"]
+                            .as_array()
+                            .unwrap()
+                            .iter()
+                            .map(|a| a.as_str().unwrap().to_string())
+                            .collect();
+
+                        if cmd.contains(&"build".to_string()) {
+                            *running = false;
+                        } else {
+                            let bind = spec["HostConfig"]["Binds"][0].as_str().unwrap();
+                            let workspace = bind.split(':').next().unwrap();
+                            let artifacts = Path::new(workspace).join("fuzz/artifacts/target");
+                            std::fs::create_dir_all(&artifacts).unwrap();
+                            std::fs::write(artifacts.join("crash-1"), b"crash").unwrap();
+                            *running = !fuzzer_exits;
+                        }
+
+                        (204, String::new())
+                    }
+                    ("GET", ["containers", id, "json"]) => {
+                        let (_, running) = &containers[*id];
+                        let state = serde_json::json!({
+                            "State": { "Running": running, "ExitCode": 0 }
+                        });
+                        (200, state.to_string())
+                    }
+                    ("POST", ["containers", id, "stop", ..]) => {
+                        containers.get_mut(*id).unwrap().1 = false;
+                        (204, String::new())
+                    }
+                    ("DELETE", ["containers", _, ..]) => (204, String::new()),
+                    _ => (404, String::new()),
+                };
+
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {} Status\r\nContent-Length: {}\r\n\r\n{}",
+                    status,
+                    response.len(),
+                    response
+                );
+            }
+        });
+
+        Self { requests, specs }
+    }
+
+    fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn specs(&self) -> Vec<serde_json::Value> {
+        self.specs.lock().unwrap().clone()
+    }
+}
+
+/// Creates a local Git-Repository that can be used as the Source of a Project
+fn create_repo(path: &Path) -> PathBuf {
+    let repo = path.join("repo");
+    std::fs::create_dir_all(repo.join("fuzz")).unwrap();
+    std::fs::write(repo.join("fuzz").join("Cargo.toml"), "").unwrap();
+
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .current_dir(&repo)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success());
+    };
+    git(&["init", "-q"]);
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "initial"]);
+
+    repo
+}
+
+fn fuzz_target(repo: &Path) -> FuzzTarget {
+    let target: Target = serde_json::from_value(serde_json::json!({
+        "name": "target",
+        "folder": "",
+        "target": { "CargoFuzz": { "name": "target" } },
+        "repeating": false,
+    }))
+    .unwrap();
+
+    FuzzTarget::new(
+        "project",
+        "target",
+        target,
+        Source::Git {
+            repo: repo.to_str().unwrap().to_string(),
+        },
+    )
+}
+
+#[tokio::test]
+async fn runs_target_in_containers() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("docker.sock");
+    let docker = FakeDocker::start(&socket, true);
+    let repo = create_repo(dir.path());
+
+    let runner = ContainerRunner::new(dir.path().join("fuzzing"), "fuzz-image")
+        .with_socket(&socket)
+        .with_memory_limit(1024);
+
+    let (events, mut events_recv) = mpsc::unbounded_channel();
+    let (_cancel, recv) = oneshot::channel();
+    let result = runner.run(fuzz_target(&repo), events, recv).await;
+
+    assert_eq!(
+        Some(RunOutput {
+            artifacts: vec![b"crash".to_vec()],
+            exceeded: None,
+        }),
+        result
+    );
+
+    let mut reported = Vec::new();
+    while let Some(event) = events_recv.recv().await {
+        reported.push(event);
+    }
+    assert_eq!(
+        vec![
+            Event::Cloning,
+            Event::Building,
+            Event::Fuzzing { workers: 1 }
+        ],
+        reported
+    );
+
+    let requests = docker.requests();
+    assert_eq!(
+        2,
+        requests
+            .iter()
+            .filter(|r| *r == "POST /containers/create")
+            .count()
+    );
+    assert!(requests.contains(&"DELETE /containers/c0?force=true".to_string()));
+    assert!(requests.contains(&"DELETE /containers/c1?force=true".to_string()));
+
+    // The Fuzzer has no Network, so it needs the Dependencies downloaded while building
+    let cargo_home = std::fs::canonicalize(dir.path().join("fuzzing").join(".cargo")).unwrap();
+    let cargo_bind = format!("{}:/cargo", cargo_home.display());
+    let specs = docker.specs();
+    // The Containers run as the User of the Host, so the Checkout can be removed again
+    let owner = std::fs::metadata(dir.path()).unwrap();
+    let user = format!("{}:{}", owner.uid(), owner.gid());
+    assert!(!dir.path().join("fuzzing/project/target").exists());
+    for spec in specs.iter() {
+        assert_eq!(user, spec["User"]);
+        assert!(spec["HostConfig"]["Binds"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!(cargo_bind)));
+        assert!(spec["Env"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!("CARGO_HOME=/cargo")));
+    }
+    let fuzzer = &specs[1];
+    assert_eq!("none", fuzzer["HostConfig"]["NetworkMode"]);
+    assert!(fuzzer["Env"]
+        .as_array()
+        .unwrap()
+        .contains(&serde_json::json!("CARGO_NET_OFFLINE=true")));
+}
+
+#[tokio::test]
+async fn failed_clone_fails_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("docker.sock");
+    let docker = FakeDocker::start(&socket, true);
+
+    let runner =
+        ContainerRunner::new(dir.path().join("fuzzing"), "fuzz-image").with_socket(&socket);
+
+    let (events, _events_recv) = mpsc::unbounded_channel();
+    let (_cancel, recv) = oneshot::channel();
+    let result = runner
+        .run(fuzz_target(&dir.path().join("missing")), events, recv)
+        .await;
+
+    assert_eq!(None, result);
+    assert!(docker.requests().is_empty());
+}
+
+#[tokio::test]
+async fn cancel_stops_containers() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("docker.sock");
+    let docker = FakeDocker::start(&socket, false);
+    let repo = create_repo(dir.path());
+
+    let runner =
+        ContainerRunner::new(dir.path().join("fuzzing"), "fuzz-image").with_socket(&socket);
+
+    let (events, _events_recv) = mpsc::unbounded_channel();
+    let (cancel, recv) = oneshot::channel();
+    cancel.send(Stop::Cancel).unwrap();
+    let result = runner.run(fuzz_target(&repo), events, recv).await;
+
+    assert_eq!(None, result);
+    assert!(docker
+        .requests()
+        .contains(&"POST /containers/c1/stop?t=5".to_string()));
+}
+
+#[tokio::test]
+async fn budget_keeps_artifacts() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("docker.sock");
+    let docker = FakeDocker::start(&socket, false);
+    let repo = create_repo(dir.path());
+
+    let runner =
+        ContainerRunner::new(dir.path().join("fuzzing"), "fuzz-image").with_socket(&socket);
+
+    let (events, _events_recv) = mpsc::unbounded_channel();
+    let (cancel, recv) = oneshot::channel();
+    cancel.send(Stop::Budget).unwrap();
+    let result = runner.run(fuzz_target(&repo), events, recv).await;
+
+    assert_eq!(
+        Some(RunOutput {
+            artifacts: vec![b"crash".to_vec()],
+            exceeded: None,
+        }),
+        result
+    );
+    assert!(docker
+        .requests()
+        .contains(&"POST /containers/c1/stop?t=5".to_string()));
+}
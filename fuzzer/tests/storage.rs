@@ -0,0 +1,1017 @@
+//! Checks that all the Storage-Backends behave the same way, by running the same Checks against
+//! every Backend through the [`StorageHandle`]
+//!
+//! The PostgreSQL Backend is only checked if `CFUZZ_TEST_POSTGRES` contains the Connection-String
+//! of a Database, in which the Tests can create their own Databases.
+
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use cfuzz::{
+    auth::{ApiToken, Member, Role, Scope, Session, User},
+    manifest::{self, Manifest},
+    project::{CargoFuzzOptions, Limits, Project, RunTarget, Sanitizer, Source, Target},
+    storage::{
+        self,
+        blobs::{self as blob_store, FsBlobStore},
+        memory::InMemoryBackend,
+        postgres::PostgresBackend,
+        sqlite::SqliteBackend,
+        StorageError, StorageHandle,
+    },
+    FuzzResult,
+};
+
+/// Generates a Test for every Check that uses a new Handle for the Backend, the Tests are skipped
+/// if the Backend is not available
+macro_rules! conformance {
+    ($backend:ident, $create:expr) => {
+        mod $backend {
+            use super::*;
+
+            #[tokio::test]
+            async fn projects() {
+                let (store, _guard) = match $create {
+                    Some(s) => s,
+                    None => return,
+                };
+                super::projects(&store).await;
+            }
+
+            #[tokio::test]
+            async fn targets() {
+                let (store, _guard) = match $create {
+                    Some(s) => s,
+                    None => return,
+                };
+                super::targets(&store).await;
+            }
+
+            #[tokio::test]
+            async fn update_target() {
+                let (store, _guard) = match $create {
+                    Some(s) => s,
+                    None => return,
+                };
+                super::update_target(&store).await;
+            }
+
+            #[tokio::test]
+            async fn manifest_targets() {
+                let (store, _guard) = match $create {
+                    Some(s) => s,
+                    None => return,
+                };
+                super::manifest_targets(&store).await;
+            }
+
+            #[tokio::test]
+            async fn results() {
+                let (store, _guard) = match $create {
+                    Some(s) => s,
+                    None => return,
+                };
+                super::results(&store).await;
+            }
+
+            #[tokio::test]
+            async fn remove_project() {
+                let (store, _guard) = match $create {
+                    Some(s) => s,
+                    None => return,
+                };
+                super::remove_project(&store).await;
+            }
+
+            #[tokio::test]
+            async fn users() {
+                let (store, _guard) = match $create {
+                    Some(s) => s,
+                    None => return,
+                };
+                super::users(&store).await;
+            }
+
+            #[tokio::test]
+            async fn sessions() {
+                let (store, _guard) = match $create {
+                    Some(s) => s,
+                    None => return,
+                };
+                super::sessions(&store).await;
+            }
+
+            #[tokio::test]
+            async fn tokens() {
+                let (store, _guard) = match $create {
+                    Some(s) => s,
+                    None => return,
+                };
+                super::tokens(&store).await;
+            }
+
+            #[tokio::test]
+            async fn members() {
+                let (store, _guard) = match $create {
+                    Some(s) => s,
+                    None => return,
+                };
+                super::members(&store).await;
+            }
+
+            #[tokio::test]
+            async fn blobs() {
+                let (store, _guard) = match $create {
+                    Some(s) => s,
+                    None => return,
+                };
+                let dir = tempfile::tempdir().unwrap();
+                let store = store.with_blobs(Arc::new(FsBlobStore::new(dir.path())));
+                super::blobs(&store, dir.path()).await;
+            }
+        }
+    };
+}
+
+conformance!(memory, Some((storage::start(InMemoryBackend::new()), ())));
+conformance!(sqlite, {
+    let dir = tempfile::tempdir().unwrap();
+    let store = storage::start(SqliteBackend::new(dir.path().join("data.db")).unwrap());
+    Some((store, dir))
+});
+conformance!(postgres, TestDatabase::create().await);
+
+/// A Database created before the Migrations existed keeps its Data
+#[tokio::test]
+async fn sqlite_migrations() {
+    migrate_legacy(
+        "
+        CREATE TABLE results (pname string, tname string, input binary);
+        CREATE TABLE projects (name string primary key, source string);
+        CREATE TABLE targets (pname string, name string, folder string, target string, PRIMARY KEY (pname, name));
+        INSERT INTO results VALUES ('first', 'parse', x'000102');
+        ",
+    )
+    .await;
+}
+
+/// A Database created before the Blob-Store and the Migrations existed already contains the
+/// Column of the second Migration
+#[tokio::test]
+async fn sqlite_migrations_sanitizer() {
+    migrate_legacy(
+        "
+        CREATE TABLE results (pname string, tname string, input binary, sanitizer string);
+        CREATE TABLE projects (name string primary key, source string);
+        CREATE TABLE targets (pname string, name string, folder string, target string, PRIMARY KEY (pname, name));
+        INSERT INTO results VALUES ('first', 'parse', x'000102', NULL);
+        ",
+    )
+    .await;
+}
+
+/// A Database created with the Blob-Store, but before the Migrations existed, already contains
+/// the Columns of the second and third Migration
+#[tokio::test]
+async fn sqlite_migrations_blob() {
+    migrate_legacy(
+        "
+        CREATE TABLE results (pname string, tname string, input binary, sanitizer string, blob string);
+        CREATE TABLE projects (name string primary key, source string);
+        CREATE TABLE targets (pname string, name string, folder string, target string, PRIMARY KEY (pname, name));
+        CREATE TABLE users (name string primary key, password_hash string, admin bool);
+        CREATE TABLE sessions (token_hash string primary key, user string, expires integer);
+        CREATE TABLE tokens (user string, name string, token_hash string unique, scopes string, created integer, PRIMARY KEY (user, name));
+        CREATE TABLE members (pname string, user string, role string, PRIMARY KEY (pname, user));
+        INSERT INTO results VALUES ('first', 'parse', x'000102', NULL, NULL);
+        ",
+    )
+    .await;
+}
+
+/// Creates a Database with the given Schema and a single Project, that has to be kept when
+/// starting the Backend on it
+async fn migrate_legacy(schema: &str) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data.db");
+
+    let legacy = rusqlite::Connection::open(&path).unwrap();
+    legacy.execute_batch(schema).unwrap();
+    legacy
+        .execute_batch(
+            "
+            INSERT INTO projects VALUES ('first', '{\"Git\":{\"repo\":\"repo-1\"}}');
+            INSERT INTO targets VALUES ('first', 'parse', '.', '{\"CargoFuzz\":{\"name\":\"parse\"}}');
+            ",
+        )
+        .unwrap();
+    drop(legacy);
+
+    // Starting twice makes sure that the Migrations are only applied once
+    for _ in 0..2 {
+        let store = storage::start(SqliteBackend::new(&path).unwrap());
+
+        let loaded = store.load_project("first").await.unwrap().unwrap();
+        assert_eq!(project("first", "repo-1").source, loaded.source);
+        assert_eq!(vec![target("parse", ".")], loaded.targets);
+        assert_eq!(
+            vec![FuzzResult::new("parse".to_string(), vec![0, 1, 2], None)],
+            store.load_results("first".to_string()).await.unwrap()
+        );
+    }
+
+    let version: usize = rusqlite::Connection::open(&path)
+        .unwrap()
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(storage::sqlite::MIGRATIONS.len(), version);
+}
+
+/// A File that is not a Database is reported when opening it
+#[test]
+fn sqlite_invalid() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data.db");
+    std::fs::write(&path, "not a database, but long enough to contain a header").unwrap();
+
+    assert!(SqliteBackend::new(&path).is_err());
+}
+
+/// Corrupt Data is reported as an Error, without stopping the Backend for later Requests
+#[tokio::test]
+async fn sqlite_corrupt() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data.db");
+
+    let store = storage::start(SqliteBackend::new(&path).unwrap());
+    store
+        .update_project(project("first", "repo-1"))
+        .await
+        .unwrap();
+
+    rusqlite::Connection::open(&path)
+        .unwrap()
+        .execute(
+            "UPDATE projects SET source = 'not json' WHERE name = 'first'",
+            [],
+        )
+        .unwrap();
+
+    assert!(matches!(
+        store.load_project("first").await,
+        Err(StorageError::Corrupt(_))
+    ));
+    assert!(matches!(
+        store.load_projects().await,
+        Err(StorageError::Corrupt(_))
+    ));
+
+    store
+        .update_project(project("second", "repo-2"))
+        .await
+        .unwrap();
+    assert!(store.load_project("second").await.unwrap().is_some());
+}
+
+/// Columns with unexpected Values are reported as corrupt Data instead of stopping the Backend
+#[tokio::test]
+async fn postgres_corrupt() {
+    let (store, database) = match TestDatabase::create().await {
+        Some(s) => s,
+        None => return,
+    };
+    store
+        .update_project(project("first", "repo-1"))
+        .await
+        .unwrap();
+
+    database
+        .execute(
+            "ALTER TABLE projects ALTER COLUMN source DROP NOT NULL; UPDATE projects SET source = NULL WHERE name = 'first'",
+        )
+        .await;
+
+    assert!(matches!(
+        store.load_project("first").await,
+        Err(StorageError::Corrupt(_))
+    ));
+    assert!(matches!(
+        store.load_projects().await,
+        Err(StorageError::Corrupt(_))
+    ));
+
+    store
+        .update_project(project("second", "repo-2"))
+        .await
+        .unwrap();
+    assert!(store.load_project("second").await.unwrap().is_some());
+}
+
+/// A new Database on the Server from `CFUZZ_TEST_POSTGRES`, which is dropped again at the End of
+/// the Test
+struct TestDatabase {
+    config: tokio_postgres::Config,
+    name: String,
+}
+
+impl TestDatabase {
+    async fn create() -> Option<(StorageHandle, Self)> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let url = std::env::var("CFUZZ_TEST_POSTGRES").ok()?;
+        let config: tokio_postgres::Config = url.parse().unwrap();
+        let name = format!(
+            "cfuzz_test_{}_{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+
+        execute(&config, format!("CREATE DATABASE {}", name)).await;
+
+        let mut database = config.clone();
+        database.dbname(&name);
+        let backend = PostgresBackend::with_config(database, 4).await.unwrap();
+
+        Some((storage::start(backend), Self { config, name }))
+    }
+
+    /// Runs the Statements directly on the Database, bypassing the Backend
+    async fn execute(&self, statements: &str) {
+        let mut database = self.config.clone();
+        database.dbname(&self.name);
+        execute(&database, statements.to_string()).await;
+    }
+}
+
+impl Drop for TestDatabase {
+    fn drop(&mut self) {
+        let config = self.config.clone();
+        let statement = format!("DROP DATABASE {} WITH (FORCE)", self.name);
+
+        // The Runtime of the Test can not be used from within Drop
+        std::thread::spawn(move || {
+            tokio::runtime::Runtime::new()
+                .unwrap()
+                .block_on(execute(&config, statement));
+        })
+        .join()
+        .unwrap();
+    }
+}
+
+async fn execute(config: &tokio_postgres::Config, statement: String) {
+    let (client, connection) = config.connect(tokio_postgres::NoTls).await.unwrap();
+    tokio::spawn(connection);
+
+    client.batch_execute(&statement).await.unwrap();
+}
+
+fn project(name: &str, repo: &str) -> Project {
+    Project {
+        name: name.to_string(),
+        source: Source::Git {
+            repo: repo.to_string(),
+        },
+        targets: Vec::new(),
+    }
+}
+
+fn target(name: &str, folder: &str) -> Target {
+    Target {
+        name: name.to_string(),
+        folder: folder.to_string(),
+        target: RunTarget::CargoFuzz {
+            name: name.to_string(),
+            options: CargoFuzzOptions::default(),
+        },
+        repeating: false,
+        sanitizers: Vec::new(),
+        workers: 1,
+        limits: Limits::default(),
+        budget_secs: None,
+        interval_secs: None,
+    }
+}
+
+fn member(project: &str, user: &str, role: Role) -> Member {
+    Member {
+        project: project.to_string(),
+        user: user.to_string(),
+        role,
+    }
+}
+
+fn names(projects: &[Project]) -> Vec<&str> {
+    let mut names: Vec<_> = projects.iter().map(|p| p.name.as_str()).collect();
+    names.sort_unstable();
+    names
+}
+
+async fn projects(store: &StorageHandle) {
+    assert!(store.load_projects().await.unwrap().is_empty());
+    assert!(store.load_project("first").await.unwrap().is_none());
+
+    store
+        .update_project(project("first", "repo-1"))
+        .await
+        .unwrap();
+    store
+        .update_project(project("second", "repo-2"))
+        .await
+        .unwrap();
+    assert_eq!(
+        vec!["first", "second"],
+        names(&store.load_projects().await.unwrap())
+    );
+
+    let first = store.load_project("first").await.unwrap().unwrap();
+    assert_eq!("first", first.name);
+    assert_eq!(
+        Source::Git {
+            repo: "repo-1".to_string()
+        },
+        first.source
+    );
+    assert!(first.targets.is_empty());
+
+    // Updating the Project replaces its Source but keeps its Targets
+    store
+        .add_project_target("first".to_string(), target("parse", "."))
+        .await
+        .unwrap();
+    store
+        .update_project(project("first", "repo-3"))
+        .await
+        .unwrap();
+
+    let first = store.load_project("first").await.unwrap().unwrap();
+    assert_eq!(
+        Source::Git {
+            repo: "repo-3".to_string()
+        },
+        first.source
+    );
+    assert_eq!(vec![target("parse", ".")], first.targets);
+    assert_eq!(2, store.load_projects().await.unwrap().len());
+}
+
+async fn targets(store: &StorageHandle) {
+    store
+        .update_project(project("first", "repo-1"))
+        .await
+        .unwrap();
+    store
+        .update_project(project("second", "repo-2"))
+        .await
+        .unwrap();
+
+    store
+        .add_project_target("first".to_string(), target("parse", "."))
+        .await
+        .unwrap();
+    store
+        .add_project_target("first".to_string(), target("lex", "."))
+        .await
+        .unwrap();
+    store
+        .add_project_target("second".to_string(), target("parse", "."))
+        .await
+        .unwrap();
+
+    let mut targets = store.load_project("first").await.unwrap().unwrap().targets;
+    targets.sort_by(|a, b| a.name.cmp(&b.name));
+    assert_eq!(vec![target("lex", "."), target("parse", ".")], targets);
+
+    // Adding a Target with the same Name replaces it
+    store
+        .add_project_target("first".to_string(), target("parse", "sub"))
+        .await
+        .unwrap();
+    let targets = store.load_project("first").await.unwrap().unwrap().targets;
+    assert_eq!(2, targets.len());
+    assert!(targets.contains(&target("parse", "sub")));
+
+    store
+        .remove_project_target("first".to_string(), "parse".to_string())
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![target("lex", ".")],
+        store.load_project("first").await.unwrap().unwrap().targets
+    );
+
+    // The Target of the other Project is not affected
+    assert_eq!(
+        vec![target("parse", ".")],
+        store.load_project("second").await.unwrap().unwrap().targets
+    );
+
+    let projects = store.load_projects().await.unwrap();
+    let second = projects.iter().find(|p| p.name == "second").unwrap();
+    assert_eq!(vec![target("parse", ".")], second.targets);
+
+    // Changing only the Workers of a Target is stored as well
+    let parallel = Target {
+        workers: 8,
+        ..target("parse", ".")
+    };
+    store
+        .add_project_target("second".to_string(), parallel.clone())
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![parallel],
+        store.load_project("second").await.unwrap().unwrap().targets
+    );
+
+    // Every Field of the Target is stored
+    let configured = Target {
+        repeating: true,
+        sanitizers: vec![Sanitizer::Address, Sanitizer::Memory],
+        workers: 4,
+        limits: Limits {
+            memory_mb: Some(2048),
+            cpus: Some(1.5),
+            disk_mb: None,
+        },
+        budget_secs: Some(600),
+        interval_secs: Some(3600),
+        ..target("lex", ".")
+    };
+    store
+        .add_project_target("second".to_string(), configured.clone())
+        .await
+        .unwrap();
+    assert!(store
+        .load_project("second")
+        .await
+        .unwrap()
+        .unwrap()
+        .targets
+        .contains(&configured));
+    assert_eq!(
+        Some(configured),
+        store
+            .load_target("second".to_string(), "lex".to_string())
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        Some(target("lex", ".")),
+        store
+            .load_target("first".to_string(), "lex".to_string())
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        None,
+        store
+            .load_target("first".to_string(), "parse".to_string())
+            .await
+            .unwrap()
+    );
+}
+
+async fn update_target(store: &StorageHandle) {
+    store
+        .update_project(project("first", "repo-1"))
+        .await
+        .unwrap();
+    store
+        .add_project_target("first".to_string(), target("parse", "."))
+        .await
+        .unwrap();
+
+    let mut updated = target("parse", "sub");
+    updated.workers = 4;
+    updated.budget_secs = Some(60);
+    assert!(store
+        .update_project_target("first".to_string(), updated.clone())
+        .await
+        .unwrap());
+    assert_eq!(
+        vec![updated],
+        store.load_project("first").await.unwrap().unwrap().targets
+    );
+
+    // Updating never creates a Target
+    assert!(!store
+        .update_project_target("first".to_string(), target("lex", "."))
+        .await
+        .unwrap());
+    assert!(!store
+        .update_project_target("second".to_string(), target("parse", "."))
+        .await
+        .unwrap());
+    let targets = store.load_project("first").await.unwrap().unwrap().targets;
+    assert_eq!(1, targets.len());
+    assert!(store
+        .load_target("second".to_string(), "parse".to_string())
+        .await
+        .unwrap()
+        .is_none());
+}
+
+/// Syncing a Manifest only rewrites the Targets that are different from the stored ones, so a
+/// stored Target has to be loaded exactly as it was defined in the Manifest
+async fn manifest_targets(store: &StorageHandle) {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join(manifest::FILE),
+        r#"
+            [[targets]]
+            name = "parse"
+            folder = "."
+            repeating = true
+            sanitizers = ["address"]
+            budget_secs = 3600
+            interval_secs = 600
+
+            [targets.target.CargoFuzz]
+            name = "parse"
+            release = true
+
+            [targets.limits]
+            memory_mb = 2048
+        "#,
+    )
+    .unwrap();
+    let manifest = Manifest::load(dir.path()).unwrap().unwrap();
+
+    store
+        .update_project(project("first", "repo-1"))
+        .await
+        .unwrap();
+    for target in manifest.targets.iter() {
+        store
+            .add_project_target("first".to_string(), target.clone())
+            .await
+            .unwrap();
+    }
+
+    let stored = store.load_project("first").await.unwrap().unwrap().targets;
+    assert_eq!(manifest.targets, stored);
+}
+
+async fn results(store: &StorageHandle) {
+    store
+        .update_project(project("first", "repo-1"))
+        .await
+        .unwrap();
+    store
+        .update_project(project("second", "repo-2"))
+        .await
+        .unwrap();
+    assert!(store
+        .load_results("first".to_string())
+        .await
+        .unwrap()
+        .is_empty());
+
+    // The Sanitizer tells apart the same Crash found by different Runs of the Matrix
+    let crash = FuzzResult::new("parse".to_string(), vec![0, 1, 2], Some(Sanitizer::Address));
+    let other = FuzzResult::new("lex".to_string(), b"crash".to_vec(), None);
+    store
+        .store_result("first".to_string(), crash.clone())
+        .await
+        .unwrap();
+    store
+        .store_result("first".to_string(), other.clone())
+        .await
+        .unwrap();
+    store
+        .store_result("second".to_string(), crash.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        vec![crash.clone(), other],
+        store.load_results("first".to_string()).await.unwrap()
+    );
+    assert_eq!(
+        vec![crash],
+        store.load_results("second".to_string()).await.unwrap()
+    );
+}
+
+async fn blobs(store: &StorageHandle, root: &Path) {
+    store
+        .update_project(project("first", "repo-1"))
+        .await
+        .unwrap();
+
+    let crash = FuzzResult::new("parse".to_string(), vec![0, 1, 2], None);
+    let other = FuzzResult::new("lex".to_string(), b"crash".to_vec(), None);
+    store
+        .store_result("first".to_string(), crash.clone())
+        .await
+        .unwrap();
+    store
+        .store_result("first".to_string(), other.clone())
+        .await
+        .unwrap();
+
+    // The Inputs are only stored in the Blob-Store
+    for content in [&[0, 1, 2][..], b"crash"] {
+        let hash = blob_store::hash(content);
+        let path = root.join(&hash[..2]).join(&hash);
+        assert_eq!(content, std::fs::read(path).unwrap());
+    }
+
+    // The Blob is not part of the serialized Result, so only the Name and Content are compared
+    let loaded = store.load_results("first".to_string()).await.unwrap();
+    assert_eq!(
+        serde_json::to_value(vec![crash, other]).unwrap(),
+        serde_json::to_value(loaded).unwrap()
+    );
+}
+
+async fn remove_project(store: &StorageHandle) {
+    for name in ["first", "second"] {
+        store.update_project(project(name, "repo")).await.unwrap();
+        store
+            .add_project_target(name.to_string(), target("parse", "."))
+            .await
+            .unwrap();
+        store
+            .store_result(
+                name.to_string(),
+                FuzzResult::new("parse".to_string(), vec![1], None),
+            )
+            .await
+            .unwrap();
+        store
+            .store_member(member(name, "user", Role::Viewer))
+            .await
+            .unwrap();
+    }
+
+    store.remove_project("first".to_string()).await.unwrap();
+
+    assert!(store.load_project("first").await.unwrap().is_none());
+    assert_eq!(vec!["second"], names(&store.load_projects().await.unwrap()));
+    assert!(store
+        .load_results("first".to_string())
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(store
+        .load_members("first".to_string())
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        vec![member("second", "user", Role::Viewer)],
+        store.load_memberships("user".to_string()).await.unwrap()
+    );
+
+    // Creating the Project again does not bring back its Targets
+    store
+        .update_project(project("first", "repo"))
+        .await
+        .unwrap();
+    assert!(store
+        .load_project("first")
+        .await
+        .unwrap()
+        .unwrap()
+        .targets
+        .is_empty());
+    assert_eq!(
+        1,
+        store
+            .load_results("second".to_string())
+            .await
+            .unwrap()
+            .len()
+    );
+}
+
+async fn users(store: &StorageHandle) {
+    assert!(store.load_users().await.unwrap().is_empty());
+    assert!(store
+        .load_user("admin".to_string())
+        .await
+        .unwrap()
+        .is_none());
+
+    let admin = User {
+        name: "admin".to_string(),
+        password_hash: "hash-1".to_string(),
+        admin: true,
+    };
+    let user = User {
+        name: "user".to_string(),
+        password_hash: "hash-2".to_string(),
+        admin: false,
+    };
+    store.store_user(admin.clone()).await.unwrap();
+    store.store_user(user.clone()).await.unwrap();
+
+    assert_eq!(
+        Some(admin),
+        store.load_user("admin".to_string()).await.unwrap()
+    );
+
+    // Storing a User with the same Name replaces it
+    let promoted = User {
+        admin: true,
+        ..user
+    };
+    store.store_user(promoted.clone()).await.unwrap();
+    assert_eq!(
+        Some(promoted),
+        store.load_user("user".to_string()).await.unwrap()
+    );
+
+    let mut names: Vec<_> = store
+        .load_users()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|u| u.name)
+        .collect();
+    names.sort_unstable();
+    assert_eq!(vec!["admin", "user"], names);
+}
+
+async fn sessions(store: &StorageHandle) {
+    let session = Session {
+        token_hash: "session-hash".to_string(),
+        user: "user".to_string(),
+        expires: 1234,
+    };
+    assert!(store
+        .load_session(session.token_hash.clone())
+        .await
+        .unwrap()
+        .is_none());
+
+    store.store_session(session.clone()).await.unwrap();
+    assert_eq!(
+        Some(session.clone()),
+        store
+            .load_session(session.token_hash.clone())
+            .await
+            .unwrap()
+    );
+    assert!(store
+        .load_session("other".to_string())
+        .await
+        .unwrap()
+        .is_none());
+
+    store
+        .remove_session(session.token_hash.clone())
+        .await
+        .unwrap();
+    assert!(store
+        .load_session(session.token_hash)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+async fn tokens(store: &StorageHandle) {
+    let token = |user: &str, name: &str, hash: &str| ApiToken {
+        user: user.to_string(),
+        name: name.to_string(),
+        token_hash: hash.to_string(),
+        scopes: vec![Scope::Read, Scope::Run],
+        created: 42,
+    };
+
+    store
+        .store_token(token("user", "ci", "hash-1"))
+        .await
+        .unwrap();
+    store
+        .store_token(token("user", "laptop", "hash-2"))
+        .await
+        .unwrap();
+    store
+        .store_token(token("other", "ci", "hash-3"))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        Some(token("user", "ci", "hash-1")),
+        store.load_token("hash-1".to_string()).await.unwrap()
+    );
+    assert!(store
+        .load_token("missing".to_string())
+        .await
+        .unwrap()
+        .is_none());
+
+    let mut names: Vec<_> = store
+        .load_tokens("user".to_string())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|t| t.name)
+        .collect();
+    names.sort_unstable();
+    assert_eq!(vec!["ci", "laptop"], names);
+
+    // Storing a Token with the same Name replaces the old one
+    store
+        .store_token(token("user", "ci", "hash-4"))
+        .await
+        .unwrap();
+    assert!(store
+        .load_token("hash-1".to_string())
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        2,
+        store.load_tokens("user".to_string()).await.unwrap().len()
+    );
+
+    store
+        .remove_token("user".to_string(), "ci".to_string())
+        .await
+        .unwrap();
+    assert!(store
+        .load_token("hash-4".to_string())
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        vec![token("user", "laptop", "hash-2")],
+        store.load_tokens("user".to_string()).await.unwrap()
+    );
+    assert_eq!(
+        vec![token("other", "ci", "hash-3")],
+        store.load_tokens("other".to_string()).await.unwrap()
+    );
+}
+
+async fn members(store: &StorageHandle) {
+    store
+        .update_project(project("first", "repo-1"))
+        .await
+        .unwrap();
+    store
+        .update_project(project("second", "repo-2"))
+        .await
+        .unwrap();
+
+    store
+        .store_member(member("first", "user", Role::Viewer))
+        .await
+        .unwrap();
+    store
+        .store_member(member("second", "user", Role::Viewer))
+        .await
+        .unwrap();
+    store
+        .store_member(member("first", "other", Role::Viewer))
+        .await
+        .unwrap();
+
+    // Storing the Member again changes the Role
+    store
+        .store_member(member("first", "user", Role::Maintainer))
+        .await
+        .unwrap();
+
+    let mut members = store.load_members("first".to_string()).await.unwrap();
+    members.sort_by(|a, b| a.user.cmp(&b.user));
+    assert_eq!(
+        vec![
+            member("first", "other", Role::Viewer),
+            member("first", "user", Role::Maintainer)
+        ],
+        members
+    );
+
+    let mut memberships = store.load_memberships("user".to_string()).await.unwrap();
+    memberships.sort_by(|a, b| a.project.cmp(&b.project));
+    assert_eq!(
+        vec![
+            member("first", "user", Role::Maintainer),
+            member("second", "user", Role::Viewer)
+        ],
+        memberships
+    );
+
+    store
+        .remove_member("first".to_string(), "user".to_string())
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![member("first", "other", Role::Viewer)],
+        store.load_members("first".to_string()).await.unwrap()
+    );
+    assert_eq!(
+        vec![member("second", "user", Role::Viewer)],
+        store.load_memberships("user".to_string()).await.unwrap()
+    );
+}